@@ -0,0 +1,496 @@
+use crate::structural::{ChangeKind, StructuralChange};
+use crate::LazyDiffView;
+use serde::Serialize;
+use serde_json::Value;
+use similar::DiffOp;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::ops::Range;
+
+// --- HEADLESS OUTPUT FORMATS ---
+
+/// Writes the ops in classic `diff` normal format (`2,3c2`, `< old`, `---`, `> new`).
+pub fn write_text<W: Write>(out: &mut W, f1: &LazyDiffView, f2: &LazyDiffView, ops: &[DiffOp]) -> io::Result<()> {
+    for op in ops {
+        match *op {
+            DiffOp::Equal { .. } => {}
+            DiffOp::Delete { old_index, old_len, new_index } => {
+                writeln!(out, "{}d{}", line_range(old_index, old_len), new_index)?;
+                write_lines(out, "< ", f1, old_index, old_len)?;
+            }
+            DiffOp::Insert { old_index, new_index, new_len } => {
+                writeln!(out, "{}a{}", old_index, line_range(new_index, new_len))?;
+                write_lines(out, "> ", f2, new_index, new_len)?;
+            }
+            DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+                writeln!(out, "{}c{}", line_range(old_index, old_len), line_range(new_index, new_len))?;
+                write_lines(out, "< ", f1, old_index, old_len)?;
+                writeln!(out, "---")?;
+                write_lines(out, "> ", f2, new_index, new_len)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes a unified diff (`--- a/`, `+++ b/`, `@@` hunks) that `patch` and
+/// `git apply` accept. `labels` go on the `---`/`+++` lines as they are.
+pub fn write_unified<W: Write>(out: &mut W, f1: &LazyDiffView, f2: &LazyDiffView, ops: &[DiffOp], labels: (&str, &str), context: usize) -> io::Result<()> {
+    let old = Side::new(f1);
+    let new = Side::new(f2);
+    let mut hunks = HunkWriter { out, old: &old, new: &new, context, lines: Vec::new(), before: VecDeque::new(), trailing: 0, labels: Some(labels) };
+
+    for op in ops {
+        match *op {
+            DiffOp::Equal { old_index, new_index, len } => {
+                let mut k = 0;
+                while k < len {
+                    // Outside a hunk only the last `context` lines of the run can matter
+                    if hunks.lines.is_empty() && len - k > context {
+                        k = len - context;
+                        hunks.before.clear();
+                        continue;
+                    }
+                    hunks.equal(old_index + k, new_index + k)?;
+                    k += 1;
+                }
+            }
+            DiffOp::Delete { old_index, old_len, new_index } => {
+                for o in old_index..old_index + old_len {
+                    hunks.removed(o, new_index)?;
+                }
+            }
+            DiffOp::Insert { old_index, new_index, new_len } => {
+                for n in new_index..new_index + new_len {
+                    hunks.added(old_index, n)?;
+                }
+            }
+            DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+                for o in old_index..old_index + old_len {
+                    hunks.removed(o, new_index)?;
+                }
+                for n in new_index..new_index + new_len {
+                    hunks.added(old_index + old_len, n)?;
+                }
+            }
+        }
+    }
+    hunks.flush()
+}
+
+// Real lines of one input. Our views end in an empty phantom line when the
+// file ends with a newline; without one, the last line needs a marker.
+struct Side<'a> {
+    view: &'a LazyDiffView,
+    lines: usize,
+    terminated: bool,
+}
+
+impl<'a> Side<'a> {
+    fn new(view: &'a LazyDiffView) -> Self {
+        let terminated = view.content.last() == Some(&b'\n');
+        let lines = if view.content.is_empty() { 0 } else if terminated { view.len() - 1 } else { view.len() };
+        Self { view, lines, terminated }
+    }
+
+    fn is_real(&self, idx: usize) -> bool {
+        idx < self.lines
+    }
+
+    fn lacks_newline(&self, idx: usize) -> bool {
+        !self.terminated && idx + 1 == self.lines
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tag {
+    Context,
+    Removed,
+    Added,
+}
+
+// A hunk line with its position on both sides: the line's index on the
+// side(s) it belongs to, otherwise the number of lines before it there
+#[derive(Clone, Copy)]
+struct HunkLine {
+    tag: Tag,
+    old: usize,
+    new: usize,
+}
+
+struct HunkWriter<'a, W: Write> {
+    out: &'a mut W,
+    old: &'a Side<'a>,
+    new: &'a Side<'a>,
+    context: usize,
+    // Lines of the open hunk; empty when none is open
+    lines: Vec<HunkLine>,
+    // Unchanged lines just before the next hunk
+    before: VecDeque<HunkLine>,
+    // Unchanged lines at the end of the open hunk
+    trailing: usize,
+    // File header, written with the first hunk
+    labels: Option<(&'a str, &'a str)>,
+}
+
+impl<W: Write> HunkWriter<'_, W> {
+    fn equal(&mut self, o: usize, n: usize) -> io::Result<()> {
+        match (self.old.is_real(o), self.new.is_real(n)) {
+            (false, false) => Ok(()),
+            (true, false) => self.removed(o, n),
+            (false, true) => self.added(o, n),
+            // Same text, but only one side ends without a newline
+            (true, true) if self.old.lacks_newline(o) != self.new.lacks_newline(n) => {
+                self.removed(o, n)?;
+                self.added(o + 1, n)
+            }
+            (true, true) => self.unchanged(HunkLine { tag: Tag::Context, old: o, new: n }),
+        }
+    }
+
+    fn removed(&mut self, o: usize, n: usize) -> io::Result<()> {
+        if self.old.is_real(o) {
+            self.change(HunkLine { tag: Tag::Removed, old: o, new: n });
+        }
+        Ok(())
+    }
+
+    fn added(&mut self, o: usize, n: usize) -> io::Result<()> {
+        if self.new.is_real(n) {
+            self.change(HunkLine { tag: Tag::Added, old: o, new: n });
+        }
+        Ok(())
+    }
+
+    fn unchanged(&mut self, line: HunkLine) -> io::Result<()> {
+        if self.lines.is_empty() {
+            if self.before.len() == self.context {
+                self.before.pop_front();
+            }
+            if self.context > 0 {
+                self.before.push_back(line);
+            }
+            return Ok(());
+        }
+        self.lines.push(line);
+        self.trailing += 1;
+        // Enough unchanged lines to end this hunk and lead into the next one
+        if self.trailing > 2 * self.context {
+            let lead: Vec<HunkLine> = self.lines.split_off(self.lines.len() - self.context);
+            self.trailing -= self.context;
+            self.flush()?;
+            self.before.extend(lead);
+        }
+        Ok(())
+    }
+
+    fn change(&mut self, line: HunkLine) {
+        if self.lines.is_empty() {
+            self.lines.extend(self.before.drain(..));
+        }
+        self.lines.push(line);
+        self.trailing = 0;
+    }
+
+    /// Writes the open hunk, trimmed to `context` trailing lines.
+    fn flush(&mut self) -> io::Result<()> {
+        self.lines.truncate(self.lines.len() - self.trailing.saturating_sub(self.context));
+        self.trailing = 0;
+        let Some(first) = self.lines.first().copied() else { return Ok(()) };
+        if let Some((a, b)) = self.labels.take() {
+            writeln!(self.out, "--- {}", a)?;
+            writeln!(self.out, "+++ {}", b)?;
+        }
+
+        let old_len = self.lines.iter().filter(|l| l.tag != Tag::Added).count();
+        let new_len = self.lines.iter().filter(|l| l.tag != Tag::Removed).count();
+        writeln!(self.out, "@@ -{} +{} @@", hunk_range(first.old, old_len), hunk_range(first.new, new_len))?;
+        for line in std::mem::take(&mut self.lines) {
+            let (prefix, side, idx) = match line.tag {
+                Tag::Context => (' ', self.old, line.old),
+                Tag::Removed => ('-', self.old, line.old),
+                Tag::Added => ('+', self.new, line.new),
+            };
+            writeln!(self.out, "{}{}", prefix, side.view.get_line(idx).unwrap_or(""))?;
+            if side.lacks_newline(idx) {
+                writeln!(self.out, "\\ No newline at end of file")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// "start,len" with 1-based start; an empty range names the line before it
+fn hunk_range(index: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", index),
+        1 => format!("{}", index + 1),
+        _ => format!("{},{}", index + 1, len),
+    }
+}
+
+/// Writes one line per structural change: marker, JSON path and values.
+pub fn write_structural<W: Write>(out: &mut W, changes: &[StructuralChange]) -> io::Result<()> {
+    for change in changes {
+        writeln!(out, "{} {} {}", change.kind.marker(), change.path, change.kind.describe())?;
+    }
+    Ok(())
+}
+
+/// Version of the `--format json` document. Bump it on any change that could
+/// break a consumer (renamed or removed fields, changed meaning); adding
+/// fields does not need a bump. The schema is documented in the README.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The `--format json` document. Field order is the output order.
+#[derive(Serialize)]
+pub struct JsonReport<'a> {
+    pub schema_version: u32,
+    pub old_file: &'a str,
+    pub new_file: &'a str,
+    pub identical: bool,
+    pub summary: Summary,
+    pub line_changes: Vec<LineChange<'a>>,
+    /// None unless both inputs are JSON
+    pub structural_changes: Option<Vec<JsonChange<'a>>>,
+}
+
+#[derive(Serialize)]
+pub struct Summary {
+    pub line_changes: usize,
+    pub lines_removed: usize,
+    pub lines_added: usize,
+    // Structural counts are null unless both inputs are JSON
+    pub structural_changes: Option<usize>,
+    pub added: Option<usize>,
+    pub removed: Option<usize>,
+    pub changed: Option<usize>,
+    pub type_changed: Option<usize>,
+    pub moved: Option<usize>,
+}
+
+/// One `DiffOp` other than Equal.
+#[derive(Serialize)]
+pub struct LineChange<'a> {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub old_lines: LineSpan,
+    pub new_lines: LineSpan,
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub old: Vec<&'a str>,
+    pub new: Vec<&'a str>,
+}
+
+/// 1-based `start` and a `count`; an empty span starts at the line before it.
+#[derive(Serialize)]
+pub struct LineSpan {
+    pub start: usize,
+    pub count: usize,
+}
+
+#[derive(Serialize)]
+pub struct JsonChange<'a> {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub path: String,
+    pub pointer: String,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    // Only present (true) when `old` or `new` is a size summary, not the value
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub summarised: bool,
+}
+
+/// Builds the `--format json` document: summary counts, every line-level
+/// change, and the structural changes when both inputs are JSON.
+pub fn to_json_report<'a>(f1: &'a LazyDiffView, f2: &'a LazyDiffView, ops: &[DiffOp], changes: Option<&'a [StructuralChange]>, names: (&'a str, &'a str)) -> JsonReport<'a> {
+    let path_at = |view: &LazyDiffView, line: usize| {
+        let line = line.min(view.len().saturating_sub(1));
+        Some(view.paths.as_ref()?.path_of(view, line)?.to_string())
+    };
+    let lines = |view: &'a LazyDiffView, range: Range<usize>| -> Vec<&'a str> { range.filter_map(|i| view.get_line(i)).collect() };
+
+    let line_changes: Vec<LineChange> = ops
+        .iter()
+        .filter_map(|op| {
+            let kind = match op {
+                DiffOp::Equal { .. } => return None,
+                DiffOp::Delete { .. } => "delete",
+                DiffOp::Insert { .. } => "insert",
+                DiffOp::Replace { .. } => "replace",
+            };
+            let (old, new) = (op.old_range(), op.new_range());
+            Some(LineChange {
+                kind,
+                old_lines: LineSpan::of(&old),
+                new_lines: LineSpan::of(&new),
+                old_path: path_at(f1, old.start),
+                new_path: path_at(f2, new.start),
+                old: lines(f1, old),
+                new: lines(f2, new),
+            })
+        })
+        .collect();
+
+    let structural: Option<Vec<JsonChange>> = changes.map(|changes| {
+        let line_of = |view: &LazyDiffView, change: &StructuralChange| view.paths.as_ref()?.resolve(view, &change.path.0).map(|l| l + 1);
+        changes
+            .iter()
+            .map(|change| {
+                let (kind, old, new) = match &change.kind {
+                    ChangeKind::Added(v) => ("added", None, Some(v)),
+                    ChangeKind::Removed(v) => ("removed", Some(v), None),
+                    ChangeKind::Changed { old, new } => ("changed", Some(old), Some(new)),
+                    ChangeKind::TypeChanged { old, new } => ("type_changed", Some(old), Some(new)),
+                    ChangeKind::Moved { .. } => ("moved", None, None),
+                };
+                JsonChange {
+                    kind,
+                    path: change.path.to_string(),
+                    pointer: change.path.to_pointer(),
+                    old_line: if kind == "added" { None } else { line_of(f1, change) },
+                    new_line: if kind == "removed" { None } else { line_of(f2, change) },
+                    old,
+                    new,
+                    from: match &change.kind {
+                        ChangeKind::Moved { from } => Some(from.to_string()),
+                        _ => None,
+                    },
+                    summarised: change.summarised,
+                }
+            })
+            .collect()
+    });
+
+    let count = |kind: &str| structural.as_ref().map(|s| s.iter().filter(|c| c.kind == kind).count());
+    JsonReport {
+        schema_version: SCHEMA_VERSION,
+        old_file: names.0,
+        new_file: names.1,
+        identical: line_changes.is_empty(),
+        summary: Summary {
+            line_changes: line_changes.len(),
+            lines_removed: line_changes.iter().map(|c| c.old_lines.count).sum(),
+            lines_added: line_changes.iter().map(|c| c.new_lines.count).sum(),
+            structural_changes: structural.as_ref().map(|s| s.len()),
+            added: count("added"),
+            removed: count("removed"),
+            changed: count("changed"),
+            type_changed: count("type_changed"),
+            moved: count("moved"),
+        },
+        line_changes,
+        structural_changes: structural,
+    }
+}
+
+impl LineSpan {
+    fn of(range: &Range<usize>) -> Self {
+        let start = if range.is_empty() { range.start } else { range.start + 1 };
+        Self { start, count: range.len() }
+    }
+}
+
+// 1-based, `diff` style: "5" or "5,7"
+fn line_range(index: usize, len: usize) -> String {
+    if len == 1 {
+        format!("{}", index + 1)
+    } else {
+        format!("{},{}", index + 1, index + len)
+    }
+}
+
+fn write_lines<W: Write>(out: &mut W, prefix: &str, source: &LazyDiffView, start: usize, len: usize) -> io::Result<()> {
+    for idx in start..start + len {
+        writeln!(out, "{}{}", prefix, source.get_line(idx).unwrap_or(""))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContentSource;
+
+    fn view(text: &str) -> LazyDiffView {
+        LazyDiffView::from_source(ContentSource::Memory(text.as_bytes().to_vec())).unwrap()
+    }
+
+    #[test]
+    fn test_write_text_normal_format() {
+        let f1 = view("A\nB\nC\n");
+        let f2 = view("A\nMOD\nC\nD\n");
+        let ops = vec![
+            DiffOp::Equal { old_index: 0, new_index: 0, len: 1 },
+            DiffOp::Replace { old_index: 1, old_len: 1, new_index: 1, new_len: 1 },
+            DiffOp::Equal { old_index: 2, new_index: 2, len: 1 },
+            DiffOp::Insert { old_index: 3, new_index: 3, new_len: 1 },
+        ];
+
+        let mut out = Vec::new();
+        write_text(&mut out, &f1, &f2, &ops).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "2c2\n< B\n---\n> MOD\n3a4\n> D\n");
+    }
+
+    #[test]
+    fn test_json_report_schema() {
+        let pretty = |value: Value| {
+            let mut view = LazyDiffView::from_source(ContentSource::Memory(serde_json::to_string_pretty(&value).unwrap().into_bytes())).unwrap();
+            view.paths = crate::PathIndex::build(&view);
+            view
+        };
+        let (old, new) = (serde_json::json!({"a": 1, "b": [true]}), serde_json::json!({"a": 2, "b": [true, false]}));
+        let (f1, f2) = (pretty(old.clone()), pretty(new.clone()));
+        let ops = crate::line_diff(&f1, &f2);
+        let changes = crate::structural::diff_values(&old, &new, &Default::default());
+
+        let report = serde_json::to_value(to_json_report(&f1, &f2, &ops, Some(&changes), ("old.json", "new.json"))).unwrap();
+        let keys: Vec<&String> = report.as_object().unwrap().keys().collect();
+        assert_eq!(keys.len(), 7);
+        assert_eq!(report["schema_version"], SCHEMA_VERSION);
+        assert_eq!(report["summary"]["changed"], 1);
+        assert_eq!(report["summary"]["added"], 1);
+        assert_eq!(report["line_changes"][0]["old_lines"], serde_json::json!({"start": 2, "count": 1}));
+        assert_eq!(report["line_changes"][0]["old_path"], "$.a");
+        assert_eq!(report["structural_changes"][0], serde_json::json!({
+            "type": "changed", "path": "$.a", "pointer": "/a", "old_line": 2, "new_line": 2, "old": 1, "new": 2
+        }));
+
+        // A streamed value too large to keep is marked as a stand-in
+        let mut summarised = changes.clone();
+        summarised[1].summarised = true;
+        let report = serde_json::to_value(to_json_report(&f1, &f2, &ops, Some(&summarised), ("old.json", "new.json"))).unwrap();
+        assert_eq!((report["structural_changes"][0].get("summarised"), &report["structural_changes"][1]["summarised"]), (None, &Value::Bool(true)));
+
+        let plain = serde_json::to_value(to_json_report(&f1, &f1, &crate::line_diff(&f1, &f1), None, ("a", "a"))).unwrap();
+        assert_eq!((plain["identical"].clone(), plain["summary"]["added"].clone()), (Value::Bool(true), Value::Null));
+    }
+
+    #[test]
+    fn test_write_unified_hunks() {
+        let f1 = view("1\n2\n3\n4\n5\n6\n7\n8\n9\n");
+        let f2 = view("1\nX\n3\n4\n5\n6\n7\n8\nY");
+        let ops = crate::line_diff(&f1, &f2);
+
+        let mut out = Vec::new();
+        write_unified(&mut out, &f1, &f2, &ops, ("a/x.json", "b/x.json"), 1).unwrap();
+        let expected = "--- a/x.json\n+++ b/x.json\n@@ -1,3 +1,3 @@\n 1\n-2\n+X\n 3\n@@ -8,2 +8,2 @@\n 8\n-9\n+Y\n\\ No newline at end of file\n";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        // Enough context joins both changes into one hunk
+        let mut out = Vec::new();
+        write_unified(&mut out, &f1, &f2, &ops, ("a/x.json", "b/x.json"), 3).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().matches("@@ -").count(), 1);
+
+        let mut out = Vec::new();
+        write_unified(&mut out, &f1, &f1, &crate::line_diff(&f1, &f1), ("a", "b"), 3).unwrap();
+        assert!(out.is_empty());
+    }
+}
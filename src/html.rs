@@ -0,0 +1,203 @@
+use crate::inline::{self, Granularity};
+use crate::{group_thousands, op_row_lines, op_rows, LazyDiffView};
+use similar::DiffOp;
+use std::io::{self, Write};
+use std::ops::Range;
+
+// --- HTML REPORT ---
+// One static file with the side-by-side view: a change index, then the rows
+// of every op. Unchanged runs beyond the context lines sit in <details>
+// blocks, which browsers collapse without any script.
+
+// Above this many lines in total, folded runs are left out of the file
+const FULL_REPORT_LINES: usize = 200_000;
+
+/// CSS colors for changed lines, taken from the TUI palette.
+pub struct Palette {
+    pub del: &'static str,
+    pub del_emph: &'static str,
+    pub del_fg: &'static str,
+    pub add: &'static str,
+    pub add_emph: &'static str,
+    pub add_fg: &'static str,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Same,
+    Changed,
+}
+
+pub fn write_html<W: Write>(out: &mut W, f1: &LazyDiffView, f2: &LazyDiffView, ops: &[DiffOp], names: (&str, &str), context: usize, palette: &Palette) -> io::Result<()> {
+    let full = f1.len() + f2.len() <= FULL_REPORT_LINES;
+    let changes: Vec<usize> = (0..ops.len()).filter(|&i| !matches!(ops[i], DiffOp::Equal { .. })).collect();
+
+    writeln!(out, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{} ↔ {}</title>", escape(names.0), escape(names.1))?;
+    writeln!(out, "<style>{}</style>\n</head>\n<body>", stylesheet(palette))?;
+    writeln!(out, "<h1>{} <span class=\"arrow\">◄──►</span> {}</h1>", escape(names.0), escape(names.1))?;
+
+    let removed: usize = changes.iter().map(|&i| ops[i].old_range().len()).sum();
+    let added: usize = changes.iter().map(|&i| ops[i].new_range().len()).sum();
+    writeln!(out, "<p class=\"summary\">{} changes: {} lines removed, {} lines added.", changes.len(), group_thousands(removed), group_thousands(added))?;
+    if !full {
+        write!(out, " Large input: only changes and {} lines of context are included.", context)?;
+    }
+    writeln!(out, "</p>")?;
+
+    // Change index
+    if !changes.is_empty() {
+        writeln!(out, "<nav><ol>")?;
+        for (n, &i) in changes.iter().enumerate() {
+            let op = &ops[i];
+            let what = match op {
+                DiffOp::Delete { .. } => "Removed",
+                DiffOp::Insert { .. } => "Added",
+                _ => "Changed",
+            };
+            writeln!(out, "<li><a href=\"#change-{}\">{} {} → {}</a></li>", n + 1, what, line_span(op.old_range()), line_span(op.new_range()))?;
+        }
+        writeln!(out, "</ol></nav>")?;
+    }
+
+    writeln!(out, "<table class=\"diff\">{}", COLUMNS)?;
+    let mut change_no = 0;
+    for (i, op) in ops.iter().enumerate() {
+        if let DiffOp::Equal { len, .. } = *op {
+            // Same folding as the TUI: no leading context before the first change
+            let head = if i > 0 { context } else { 0 };
+            let tail = if i + 1 < ops.len() { context } else { 0 };
+            let hidden = len.saturating_sub(head + tail);
+            if hidden <= 1 {
+                write_rows(out, f1, f2, op, 0..len, Kind::Same)?;
+                continue;
+            }
+            write_rows(out, f1, f2, op, 0..head, Kind::Same)?;
+            writeln!(out, "</table>")?;
+            let label = format!("⋯ {} unchanged lines", group_thousands(hidden));
+            if full {
+                writeln!(out, "<details><summary>{}</summary><table class=\"diff\">{}", label, COLUMNS)?;
+                write_rows(out, f1, f2, op, head..head + hidden, Kind::Same)?;
+                writeln!(out, "</table></details>")?;
+            } else {
+                writeln!(out, "<div class=\"omitted\">{} (omitted)</div>", label)?;
+            }
+            writeln!(out, "<table class=\"diff\">{}", COLUMNS)?;
+            write_rows(out, f1, f2, op, head + hidden..len, Kind::Same)?;
+        } else {
+            change_no += 1;
+            writeln!(out, "<tbody id=\"change-{}\">", change_no)?;
+            write_rows(out, f1, f2, op, 0..op_rows(op, false), Kind::Changed)?;
+            writeln!(out, "</tbody>")?;
+        }
+    }
+    writeln!(out, "</table>\n</body>\n</html>")
+}
+
+const COLUMNS: &str = "<colgroup><col class=\"num\"><col><col class=\"num\"><col></colgroup>";
+
+fn write_rows<W: Write>(out: &mut W, f1: &LazyDiffView, f2: &LazyDiffView, op: &DiffOp, rows: Range<usize>, kind: Kind) -> io::Result<()> {
+    for local in rows {
+        let (l, r) = op_row_lines(op, local, false);
+        let (left, right) = (l.and_then(|i| f1.get_line(i)), r.and_then(|i| f2.get_line(i)));
+        // A replaced line that only moved between ops reads as unchanged
+        let changed = kind == Kind::Changed && left != right;
+        let (del, ins) = match (left, right) {
+            (Some(a), Some(b)) if changed => inline::changed_spans(a, b, Granularity::Word),
+            _ => (Vec::new(), Vec::new()),
+        };
+        write!(out, "<tr>")?;
+        write_cell(out, l, left, &del, if changed { "del" } else { "" })?;
+        write_cell(out, r, right, &ins, if changed { "add" } else { "" })?;
+        writeln!(out, "</tr>")?;
+    }
+    Ok(())
+}
+
+fn write_cell<W: Write>(out: &mut W, idx: Option<usize>, text: Option<&str>, emphasis: &[Range<usize>], class: &str) -> io::Result<()> {
+    let (Some(idx), Some(text)) = (idx, text) else {
+        return write!(out, "<td class=\"num\"></td><td class=\"empty\"></td>");
+    };
+    match class {
+        "" => write!(out, "<td class=\"num\">{}</td><td>", idx + 1)?,
+        _ => write!(out, "<td class=\"num\">{}</td><td class=\"{}\">", idx + 1, class)?,
+    }
+    let mut pos = 0;
+    for range in emphasis {
+        write!(out, "{}<em>{}</em>", escape(&text[pos..range.start]), escape(&text[range.clone()]))?;
+        pos = range.end;
+    }
+    write!(out, "{}</td>", escape(&text[pos..]))
+}
+
+// 1-based "12" or "12–15"; an empty range names the position
+fn line_span(range: Range<usize>) -> String {
+    match range.len() {
+        0 => format!("after line {}", range.start),
+        1 => format!("line {}", range.start + 1),
+        n => format!("lines {}–{}", range.start + 1, range.start + n),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn stylesheet(p: &Palette) -> String {
+    format!(
+        "body{{font-family:sans-serif;margin:1.5em;color:#222}}\
+         h1{{font-size:1.2em}} .arrow{{color:#888}} .summary{{color:#555}}\
+         nav ol{{columns:3;font-size:.9em}}\
+         table.diff{{width:100%;border-collapse:collapse;table-layout:fixed;font:12px/1.5 ui-monospace,Menlo,Consolas,monospace}}\
+         col.num{{width:4.5em}}\
+         td{{padding:0 .5em;white-space:pre-wrap;word-break:break-all;vertical-align:top}}\
+         td.num{{color:#888;text-align:right;user-select:none;border-right:1px solid #ddd}}\
+         td.empty{{background:#f4f4f4}}\
+         td.del{{background:{};color:{}}} td.del em{{background:{}}}\
+         td.add{{background:{};color:{}}} td.add em{{background:{}}}\
+         em{{font-style:normal;font-weight:bold}}\
+         tbody:target td{{outline:1px solid #fc0}}\
+         summary,.omitted{{color:#888;text-align:center;cursor:pointer;font:12px ui-monospace,Menlo,Consolas,monospace;padding:.2em}}",
+        p.del, p.del_fg, p.del_emph, p.add, p.add_fg, p.add_emph
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContentSource;
+
+    const PALETTE: Palette = Palette { del: "red", del_emph: "pink", del_fg: "white", add: "green", add_emph: "lime", add_fg: "black" };
+
+    fn view(text: &str) -> LazyDiffView {
+        LazyDiffView::from_source(ContentSource::Memory(text.as_bytes().to_vec())).unwrap()
+    }
+
+    #[test]
+    fn test_html_report() {
+        let old: String = (0..20).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 10\n", "line <10>\n");
+        let (f1, f2) = (view(&old), view(&new));
+        let ops = crate::line_diff(&f1, &f2);
+
+        let mut out = Vec::new();
+        write_html(&mut out, &f1, &f2, &ops, ("a.json", "b.json"), 2, &PALETTE).unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(html.contains("<a href=\"#change-1\">Changed line 11 → line 11</a>"));
+        assert!(html.contains("<tbody id=\"change-1\">"));
+        assert!(html.contains("<td class=\"add\">line <em>&lt;10&gt;</em></td>"));
+        // Lines 1-8 fold away before the change, and all but 2 after it
+        assert_eq!(html.matches("<summary>⋯ 8 unchanged lines</summary>").count(), 2);
+    }
+}
@@ -0,0 +1,121 @@
+use similar::{ChangeTag, TextDiff};
+use std::ops::Range;
+use std::time::Duration;
+
+// --- INTRA-LINE HIGHLIGHTING ---
+// Byte ranges that changed between the two sides of a replaced line.
+
+// Longer lines (e.g. unformatted minified JSON) are not refined
+const MAX_LINE_LEN: usize = 20_000;
+const TIMEOUT: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Granularity {
+    Word,
+    Char,
+    Off,
+}
+
+impl Granularity {
+    pub fn next(self) -> Self {
+        match self {
+            Granularity::Word => Granularity::Char,
+            Granularity::Char => Granularity::Off,
+            Granularity::Off => Granularity::Word,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Granularity::Word => "word",
+            Granularity::Char => "char",
+            Granularity::Off => "off",
+        }
+    }
+}
+
+/// Changed byte ranges in `old` and in `new`.
+pub fn changed_spans(old: &str, new: &str, granularity: Granularity) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let (mut del, mut ins) = (Vec::new(), Vec::new());
+    if old == new || old.len() > MAX_LINE_LEN || new.len() > MAX_LINE_LEN {
+        return (del, ins);
+    }
+    let (mut pos_old, mut pos_new) = (0, 0);
+
+    match granularity {
+        Granularity::Off => {}
+        Granularity::Word => {
+            // Inline changes refine a line-level replace into emphasized words
+            let diff = TextDiff::configure().timeout(TIMEOUT).diff_lines(old, new);
+            for op in diff.ops() {
+                for change in diff.iter_inline_changes(op) {
+                    for (emphasized, text) in change.iter_strings_lossy() {
+                        let (pos, spans) = match change.tag() {
+                            ChangeTag::Delete => (&mut pos_old, &mut del),
+                            ChangeTag::Insert => (&mut pos_new, &mut ins),
+                            ChangeTag::Equal => {
+                                pos_old += text.len();
+                                pos_new += text.len();
+                                continue;
+                            }
+                        };
+                        if emphasized {
+                            push_span(spans, *pos..*pos + text.len());
+                        }
+                        *pos += text.len();
+                    }
+                }
+            }
+        }
+        Granularity::Char => {
+            let diff = TextDiff::configure().timeout(TIMEOUT).diff_chars(old, new);
+            for change in diff.iter_all_changes() {
+                let len = change.value().len();
+                match change.tag() {
+                    ChangeTag::Delete => {
+                        push_span(&mut del, pos_old..pos_old + len);
+                        pos_old += len;
+                    }
+                    ChangeTag::Insert => {
+                        push_span(&mut ins, pos_new..pos_new + len);
+                        pos_new += len;
+                    }
+                    ChangeTag::Equal => {
+                        pos_old += len;
+                        pos_new += len;
+                    }
+                }
+            }
+        }
+    }
+    (del, ins)
+}
+
+// Appends a range, merging it into the previous one when they touch
+fn push_span(spans: &mut Vec<Range<usize>>, range: Range<usize>) {
+    match spans.last_mut() {
+        Some(last) if last.end == range.start => last.end = range.end,
+        _ => spans.push(range),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_spans() {
+        let old = r#"  "title": "alpha beta gamma","#;
+        let new = r#"  "title": "alpha delta gamma","#;
+
+        let (del, ins) = changed_spans(old, new, Granularity::Word);
+        assert_eq!((del.len(), ins.len()), (1, 1));
+        assert_eq!((&old[del[0].clone()], &new[ins[0].clone()]), ("beta", "delta"));
+
+        let (del, ins) = changed_spans("value: 1234", "value: 1284", Granularity::Char);
+        assert_eq!((del.len(), ins.len()), (1, 1));
+        assert_eq!((del[0].clone(), ins[0].clone()), (9..10, 9..10));
+
+        assert_eq!(changed_spans(old, new, Granularity::Off), (vec![], vec![]));
+    }
+}
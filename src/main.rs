@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use clap::Parser;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{
         disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
@@ -14,22 +14,43 @@ use ratatui::{
         Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
     },
 };
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use similar::DiffOp;
 use imara_diff::{diff, Algorithm, Sink, intern::InternedInput, sources::byte_lines};
 use std::{
+    collections::{HashMap, HashSet},
     fs,
     io,
     path::PathBuf,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    sync::Arc,
     sync::mpsc::{self, Sender},
     thread,
-    time::Duration,
+    time::{Duration, SystemTime},
     fs::File,
     io::Write,
     io::BufWriter,
+    ops::Range,
 };
 use memmap2::Mmap;
 use rayon::prelude::*;
+use unicode_width::UnicodeWidthChar;
+
+mod export;
+mod html;
+mod inline;
+mod merge;
+mod patch;
+mod path_index;
+mod search;
+mod session;
+mod stream;
+mod structural;
+mod validate;
+
+use path_index::PathIndex;
+use structural::{ArrayKey, ChangeKind, DiffOptions, JsonPath, PathPattern, StructuralChange};
 
 
 // --- GITHUB DARK MODE COLOR PALETTE ---
@@ -42,43 +63,239 @@ const HEADER_BG: Color = Color::Blue;        // Standard Blue for Header
 
 // Delete (Red)
 const BG_DEL: Color = Color::Red;            // Standard Red Background
+const BG_DEL_EMPH: Color = Color::LightRed;  // Changed words within a deleted line
 const FG_DEL: Color = Color::White;          // White text on Red
 
 // Insert (Green)
 const BG_ADD: Color = Color::Green;          // Standard Green Background
+const BG_ADD_EMPH: Color = Color::LightGreen; // Changed words within an added line
 const FG_ADD: Color = Color::Black;          // Black text on Green (High Contrast)
 
 // Empty (For alignment)
 const BG_EMPTY: Color = Color::Reset;        // Matches default bg
 
+// Search
+const BG_MATCH: Color = Color::Yellow;       // Search matches
+
 // --- CONSTANTS FOR OPTIMIZATION ---
 const MAX_JSON_FORMAT_SIZE: u64 = 300 * 1024 * 1024; // 300 MB Limit for Pretty Print
+// Streamed structural changes kept for the TUI and whole-document formats;
+// `--format structural` prints them all without keeping any
+const MAX_STREAMED_CHANGES: usize = 100_000;
+// Extra gutter for old line numbers in the unified view
+const OLD_GUTTER_WIDTH: usize = 5;
+const H_SCROLL_STEP: usize = 8; // Columns per h / l press
+// Replaced lines whose intra-line spans are kept between frames
+const MAX_CACHED_INLINE_SPANS: usize = 10_000;
+
+// Changed byte ranges on the left and on the right of a replaced line
+type InlineSpans = (Vec<Range<usize>>, Vec<Range<usize>>);
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, after_help = "
+#[command(author, version, about, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true, after_help = "
 CONTROLS:
   N / P          : Jump to Next / Previous Change
-  Range 1-3      : Resolve Conflict (1: Pick Left, 2: Pick Right, 3: Pick Both)
+  Range 1-4      : Resolve Conflict (1: Pick Left, 2: Pick Right, 3: Pick Both, 4: Edit in $EDITOR)
   Arrow Left     : Pick Left (File 1)
   Arrow Right    : Pick Right (File 2)
   Backspace      : Un-resolve (Reset)
-  S              : Save Merged Output
+  U / Ctrl+R     : Undo / Redo Resolution Changes
+  B              : Bulk Resolve (All Unresolved / This Region / JSON Path Pattern)
+  Tab            : Toggle Line / Structural View
+  /              : Search Both Panes (N / Shift+N: Next / Previous Match, Esc: Clear)
+  G              : Go to JSONPath ($.a.b[3]) or JSON Pointer (/a/b/3)
+  W              : Cycle Intra-Line Highlight (Word / Char / Off)
+  H / L / 0      : Scroll Left / Right / Back to Column 0
+  Shift+W        : Toggle Soft-Wrap
+  Z / E          : Fold Unchanged Regions (see --context) / Expand the First Visible Fold
+  V              : Toggle Split / Unified View
+  X              : Export Unified Diff (patch / git apply) or HTML Report (.html)
+  S              : Save Merged Output (checked as JSON while it is written)
+  Shift+J        : Check the Merged Output as JSON and Jump to the First Syntax Error
+  Shift+S        : Save Session (resume later with --resume)
   Q / Esc        : Quit
 ")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The first file (Base/Original)
-    file1: PathBuf,
+    #[arg(required_unless_present = "resume")]
+    file1: Option<PathBuf>,
 
     /// The second file (New/Modified)
-    file2: PathBuf,
+    #[arg(required_unless_present = "resume")]
+    file2: Option<PathBuf>,
+
+    /// Continue a session saved with Shift+S; its inputs must be unchanged
+    #[arg(long, value_name = "SESSION", conflicts_with_all = ["file1", "file2", "no_tui", "format"])]
+    resume: Option<PathBuf>,
+
+    /// Display name for file1, then file2 (like `diff -L`; useful as a git difftool)
+    #[arg(short = 'L', long = "label", num_args = 1, action = clap::ArgAction::Append)]
+    labels: Vec<String>,
+
+    /// Print the diff to stdout instead of starting the TUI (exit code 0: identical, 1: different)
+    #[arg(long)]
+    no_tui: bool,
+
+    /// Output format for headless mode (implies --no-tui)
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+
+    #[command(flatten)]
+    compare: CompareArgs,
+
+    #[command(flatten)]
+    display: DisplayArgs,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+/// Options for how the line diff is shown
+#[derive(clap::Args, Debug, Clone)]
+struct DisplayArgs {
+    /// Unchanged lines kept around each change when folding (`z` in the TUI) and in unified diffs
+    #[arg(short = 'U', long, value_name = "N", default_value_t = 3)]
+    context: usize,
+}
+
+/// Options for the structural comparison
+#[derive(clap::Args, Debug, Clone, Default)]
+struct CompareArgs {
+    /// Compare all arrays as multisets (element order is ignored)
+    #[arg(long)]
+    ignore_order: bool,
+
+    /// Compare arrays at this JSON path as multisets, e.g. `$.tags` or `users[*].roles` (repeatable)
+    #[arg(long, value_name = "PATH")]
+    ignore_order_at: Vec<PathPattern>,
+
+    /// Pair array elements by an identity field, e.g. `users[*]=id` (repeatable)
+    #[arg(long, value_name = "PATH=FIELD")]
+    array_key: Vec<ArrayKey>,
+}
+
+impl CompareArgs {
+    fn from_session(options: &session::Options) -> Result<Self> {
+        fn parse_all<T: std::str::FromStr<Err = String>>(texts: &[String]) -> Result<Vec<T>> {
+            texts.iter().map(|text| text.parse().map_err(|e| anyhow::anyhow!("Invalid option in session: {}", e))).collect()
+        }
+        Ok(CompareArgs {
+            ignore_order: options.ignore_order,
+            ignore_order_at: parse_all(&options.ignore_order_at)?,
+            array_key: parse_all(&options.array_key)?,
+        })
+    }
+
+    fn to_session(&self, context: usize) -> session::Options {
+        session::Options {
+            ignore_order: self.ignore_order,
+            ignore_order_at: self.ignore_order_at.iter().map(ToString::to_string).collect(),
+            array_key: self.array_key.iter().map(ToString::to_string).collect(),
+            context,
+        }
+    }
+
+    fn diff_options(&self) -> DiffOptions {
+        DiffOptions {
+            ignore_order: self.ignore_order,
+            unordered_paths: self.ignore_order_at.clone(),
+            array_keys: self.array_key.clone(),
+        }
+    }
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Apply an RFC 6902 JSON Patch or an RFC 7396 JSON Merge Patch to a file
+    Apply {
+        /// The file to patch
+        base: PathBuf,
+
+        /// The patch document (an array is read as JSON Patch, an object as Merge Patch)
+        patch: PathBuf,
+
+        /// Where to write the patched document
+        #[arg(short, long, default_value = "merged_output.json")]
+        output: String,
+
+        /// Treat the patch as an RFC 7396 Merge Patch even if it is an array
+        #[arg(long)]
+        merge_patch: bool,
+    },
+    /// Three-way merge: changes made on only one side relative to BASE are applied automatically
+    Merge {
+        /// The common ancestor
+        base: PathBuf,
+
+        /// Our version (shown on the left)
+        ours: PathBuf,
+
+        /// Their version (shown on the right)
+        theirs: PathBuf,
+
+        /// Output file (git's $MERGED). Saving defaults to it, and the exit code
+        /// is 0 only if it was saved with every conflict resolved
+        merged: Option<PathBuf>,
+
+        #[command(flatten)]
+        compare: CompareArgs,
+
+        #[command(flatten)]
+        display: DisplayArgs,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Debug)]
+enum OutputFormat {
+    /// Classic `diff` style line output
+    Text,
+    /// Structural changes with JSON paths
+    Structural,
+    /// RFC 6902 JSON Patch that turns file1 into file2
+    JsonPatch,
+    /// Unified diff for `patch` / `git apply` (context lines set by --context)
+    Unified,
+    /// Self-contained HTML report of the side-by-side view
+    Html,
+    /// Versioned JSON document with summary counts and every change (see README)
+    Json,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 enum Resolution {
     Unresolved,
     PickLeft,   // Keep File 1
     PickRight,  // Keep File 2
     PickBoth,   // Keep File 1 then File 2
+    Edit(String), // Hand-written replacement for the whole hunk
+}
+
+impl Resolution {
+    fn label(&self) -> &'static str {
+        match self {
+            Resolution::Unresolved => "Reset",
+            Resolution::PickLeft => "Pick Left",
+            Resolution::PickRight => "Pick Right",
+            Resolution::PickBoth => "Pick Both",
+            Resolution::Edit(_) => "Edit",
+        }
+    }
+}
+
+// What a bulk resolution applies to
+#[derive(Clone)]
+enum BulkTarget {
+    Unresolved,
+    // Changed ops between two foldable unchanged runs
+    Region(Range<usize>),
+    // Ops with a line inside a value matching the pattern (as typed, parsed)
+    Path(String, PathPattern),
+}
+
+// One undoable step: every op it touched, with the resolution before and after
+struct HistoryEntry {
+    label: String,
+    changes: Vec<(usize, Resolution, Resolution)>,
 }
 
 enum AppState {
@@ -86,11 +303,38 @@ enum AppState {
     Done,
     Error(String),
     Saving(String),
+    SavingSession(String),
+    Exporting(String),
+    // b: pick a target (None), then an action for it
+    BulkMenu(Option<BulkTarget>),
+    BulkPath(String),
+    // Description and the resolutions to set, awaiting y / n
+    BulkConfirm(String, Vec<(usize, Resolution)>),
+    Searching(String),
+    Goto(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ViewMode {
+    Lines,
+    Structural,
 }
 
 enum AppEvent {
     Log(String),
-    Done(Result<(LazyDiffView, LazyDiffView, Vec<DiffOp>)>),
+    Done(Result<Box<DiffResult>>),
+}
+
+struct DiffResult {
+    file1: LazyDiffView,
+    file2: LazyDiffView,
+    ops: Vec<DiffOp>,
+    // None when either side is not valid JSON (or too large to parse)
+    changes: Option<Vec<StructuralChange>>,
+    // Streamed changes found beyond MAX_STREAMED_CHANGES and not kept
+    changes_dropped: usize,
+    // Pre-computed resolutions (three-way merge); None means all Unresolved
+    resolutions: Option<Vec<Resolution>>,
 }
 
 
@@ -117,6 +361,12 @@ impl std::ops::Deref for ContentSource {
 struct LazyDiffView {
     content: ContentSource,
     line_offsets: Vec<usize>,
+    // Parsed document, kept until the structural diff has consumed it
+    value: Option<Value>,
+    // JSON path -> line lookup; only for documents we pretty-printed ourselves
+    paths: Option<PathIndex>,
+    // Content is our pretty-printed form, not the file's own bytes
+    reformatted: bool,
 }
 
 impl LazyDiffView {
@@ -131,19 +381,33 @@ impl LazyDiffView {
         if size > MAX_JSON_FORMAT_SIZE {
             let file = File::open(path)?;
             let mmap = unsafe { Mmap::map(&file)? };
+            // Too big to parse: re-indent minified JSON on disk instead
+            if stream::looks_like_json(&mmap) {
+                if let Ok(pretty) = pretty_print_to_temp(&mmap) {
+                    let mut view = Self::from_source(ContentSource::Mmap(pretty))?;
+                    view.paths = PathIndex::build(&view);
+                    view.reformatted = true;
+                    return Ok(view);
+                }
+            }
             return Self::from_source(ContentSource::Mmap(mmap));
         }
 
         // Small enough to check for JSON
         // Normalize line endings
         let raw_content = fs::read_to_string(path)?.replace("\r\n", "\n");
+        let mut value = None;
+        let mut reformatted = false;
         let content_bytes = if should_format_json(&raw_content) {
             if let Ok(val) = serde_json::from_str::<Value>(&raw_content) {
-                 if let Ok(pretty) = serde_json::to_string_pretty(&val) {
+                 let bytes = if let Ok(pretty) = serde_json::to_string_pretty(&val) {
+                     reformatted = pretty != raw_content;
                      pretty.into_bytes()
                  } else {
                      raw_content.into_bytes()
-                 }
+                 };
+                 value = Some(val);
+                 bytes
             } else {
                 raw_content.into_bytes()
             }
@@ -152,9 +416,26 @@ impl LazyDiffView {
             raw_content.into_bytes()
         };
 
-        Self::from_source(ContentSource::Memory(content_bytes))
+        let mut view = Self::from_source(ContentSource::Memory(content_bytes))?;
+        if value.is_some() {
+            view.paths = PathIndex::build(&view);
+        }
+        view.value = value;
+        view.reformatted = reformatted;
+        Ok(view)
     }
     
+    /// The file's own bytes, unformatted, for patches that must apply to it
+    /// as it is on disk.
+    fn raw(path: &PathBuf) -> Result<Self> {
+        let file = File::open(path)?;
+        // Mapping an empty file fails on some platforms
+        if file.metadata()?.len() == 0 {
+            return Self::from_source(ContentSource::Memory(Vec::new()));
+        }
+        Self::from_source(ContentSource::Mmap(unsafe { Mmap::map(&file)? }))
+    }
+
     fn from_source(content: ContentSource) -> Result<Self> {
          // Build line offsets (start indices of lines)
          // Parallel scanning for newlines using rayon
@@ -168,7 +449,7 @@ impl LazyDiffView {
         let mut all_offsets = vec![0];
         all_offsets.extend(offsets);
         
-        Ok(Self { content, line_offsets: all_offsets })
+        Ok(Self { content, line_offsets: all_offsets, value: None, paths: None, reformatted: false })
     }
 
     fn get_line(&self, line_idx: usize) -> Option<&str> {
@@ -196,6 +477,37 @@ impl LazyDiffView {
     fn len(&self) -> usize {
         self.line_offsets.len()
     }
+
+    /// Raw bytes of a range of lines, including their newlines.
+    fn line_bytes(&self, lines: Range<usize>) -> &[u8] {
+        let offset = |line: usize| self.line_offsets.get(line).copied().unwrap_or(self.content.len());
+        &self.content[offset(lines.start)..offset(lines.end)]
+    }
+
+    fn is_mapped(&self) -> bool {
+        matches!(self.content, ContentSource::Mmap(_))
+    }
+}
+
+// Formats into an anonymous temp file and maps it. The file is unlinked right
+// away; the mapping keeps its contents alive until the view is dropped.
+fn pretty_print_to_temp(raw: &[u8]) -> Result<Mmap> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "json-diff-{}-{}.json",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let file = fs::OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+    let result = (|| -> Result<Mmap> {
+        let mut out = BufWriter::new(&file);
+        stream::write_pretty(raw, &mut out)?;
+        out.flush()?;
+        drop(out);
+        Ok(unsafe { Mmap::map(&file)? })
+    })();
+    let _ = fs::remove_file(&path);
+    result
 }
 
 fn should_format_json(content: &str) -> bool {
@@ -203,6 +515,13 @@ fn should_format_json(content: &str) -> bool {
     trimmed.starts_with('{') || trimmed.starts_with('[')
 }
 
+enum RowContent {
+    Line(usize),
+    // Fold row standing in for this many unchanged lines
+    Fold(usize),
+}
+
+#[derive(Clone, Copy)]
 struct DiffCell {
     line_index: Option<usize>, 
     line_number: Option<usize>,
@@ -217,60 +536,197 @@ struct App {
     // Cumulative rows for each op (to map scroll -> op)
     op_row_counts: Vec<usize>, 
     
-    file1: Option<LazyDiffView>,
-    file2: Option<LazyDiffView>,
+    // Shared with background searches
+    file1: Option<Arc<LazyDiffView>>,
+    file2: Option<Arc<LazyDiffView>>,
     
     scroll_offset: usize,
     scroll_state: ScrollbarState,
+    // Horizontal scroll (chars), shared by both panes
+    h_scroll: usize,
+    // Soft-wrap: scroll_offset stays a logical row; wrap_skip hides the first
+    // visual rows of that row, and pane_width is the wrap width from the last draw.
+    // While wrapping, wrap_starts holds the visual row each logical row starts
+    // on, plus the visual total
+    wrap: bool,
+    wrap_skip: usize,
+    pane_width: usize,
+    wrap_starts: Vec<usize>,
+    // Context folding: unchanged runs collapse to `context` lines around each
+    // change plus one fold row, unless their op index is in `expanded`
+    fold: bool,
+    context: usize,
+    expanded: HashSet<usize>,
+    // Single column of `-`/`+` lines instead of two panes
+    unified: bool,
     spinner_index: usize,
     // (File1, File2, DiffOps)
     receiver: mpsc::Receiver<AppEvent>,
 
     file1_name: String,
     file2_name: String,
+    // Set for a three-way merge
+    base_name: Option<String>,
     loading_log: String,
+    // Footer message (last save result or toggle)
+    status: Option<String>,
+    // Intra-line highlighting of replaced lines, and the spans found so far
+    // for the current mode, by op and line within it
+    inline_mode: inline::Granularity,
+    inline_spans: HashMap<(usize, usize), InlineSpans>,
+
+    // Search: results of the last finished scan, the running scan (if any)
+    // and the hit n/N last jumped to
+    search: Option<search::SearchResults>,
+    search_rx: Option<mpsc::Receiver<Option<search::SearchResults>>>,
+    search_cancel: Arc<AtomicBool>,
+    search_pos: Option<usize>,
     
     // Merge State
     resolutions: Vec<Resolution>,
     selected_op_index: Option<usize>,
+    // Default path offered by the save prompt
+    output_path: String,
+    saved_all_resolved: bool,
+    // `---`/`+++` names for exported patches
+    patch_labels: (String, String),
+    // Resolution changes for u / Ctrl-r
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    // Inputs and options for Shift+S, the path it offers, and resolutions
+    // from --resume waiting for the diff to finish
+    setup: Option<session::Setup>,
+    session_path: String,
+    resumed: Option<Vec<Resolution>>,
+
+    // Structural (semantic) view
+    view_mode: ViewMode,
+    structural: Option<Vec<StructuralChange>>,
+    // Streamed changes past MAX_STREAMED_CHANGES, counted but not kept
+    structural_dropped: usize,
+    structural_scroll: usize,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
-    let f1_name = args.file1.file_name().unwrap_or_default().to_string_lossy().to_string();
-    let f2_name = args.file2.file_name().unwrap_or_default().to_string_lossy().to_string();
+    if let Some(Command::Apply { base, patch, output, merge_patch }) = &args.command {
+        if let Err(e) = run_apply(base, patch, output, *merge_patch) {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let mut merged_path = None;
+    let mut compare = args.compare.clone();
+    let mut display = args.display.clone();
+    let mut labels = args.labels.clone();
+    let mut resumed = None;
+    let (file1, file2, base) = match (&args.command, &args.resume) {
+        (Some(Command::Merge { base, ours, theirs, merged, compare: merge_compare, display: merge_display }), _) => {
+            display = merge_display.clone();
+            merged_path = merged.as_ref().map(|m| m.to_string_lossy().to_string());
+            compare = merge_compare.clone();
+            (ours.clone(), theirs.clone(), Some(base.clone()))
+        }
+        // Inputs and options come from the session; only --label may override
+        (_, Some(path)) => {
+            let session = session::Session::load(path).and_then(|session| {
+                eprintln!("Checking the inputs of {}...", path.display());
+                session.verify().map(|()| session)
+            });
+            let session = session.unwrap_or_else(|e| {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            });
+            compare = CompareArgs::from_session(&session.options)?;
+            display.context = session.options.context;
+            merged_path = session.merged;
+            if labels.is_empty() {
+                labels = session.labels;
+            }
+            resumed = Some((path.to_string_lossy().to_string(), session.resolutions));
+            (session.left.path, session.right.path, session.base.map(|b| b.path))
+        }
+        _ => {
+            // Both are required by clap unless a subcommand or --resume was given
+            let (Some(file1), Some(file2)) = (args.file1.clone(), args.file2.clone()) else {
+                unreachable!("clap enforces file1 and file2");
+            };
+            (file1, file2, None)
+        }
+    };
+    let options = compare.diff_options();
+
+    if labels.len() > 2 {
+        anyhow::bail!("--label may be given at most twice");
+    }
+    // `diff -L` style: a label replaces the whole name, otherwise git's a/ and b/ prefixes
+    let patch_label = |label: Option<&String>, prefix: &str, path: &PathBuf| label.cloned().unwrap_or_else(|| format!("{}{}", prefix, path.display()));
+    let patch_labels = (patch_label(labels.first(), "a/", &file1), patch_label(labels.get(1), "b/", &file2));
+
+    // A patch ignores these options anyway
+    if args.format != Some(OutputFormat::JsonPatch) {
+        let inputs = [Some(&file1), Some(&file2), base.as_ref()];
+        if let Err(e) = check_streaming_options(inputs.into_iter().flatten(), &options) {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(2);
+        }
+    }
+
+    if args.no_tui || args.format.is_some() {
+        let code = match run_headless(file1, file2, args.format, options, patch_labels, display.context) {
+            Ok(true) => 1,
+            Ok(false) => 0,
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                2
+            }
+        };
+        std::process::exit(code);
+    }
+
+    let f1_name = labels.first().cloned().unwrap_or_else(|| file1.file_name().unwrap_or_default().to_string_lossy().to_string());
+    let f2_name = labels.get(1).cloned().unwrap_or_else(|| file2.file_name().unwrap_or_default().to_string_lossy().to_string());
+    let base_name = base.as_ref().map(|b| b.file_name().unwrap_or_default().to_string_lossy().to_string());
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, Clear(ClearType::All), EnterAlternateScreen)?;
 
+    let setup = session::Setup {
+        base: base.clone(),
+        left: file1.clone(),
+        right: file2.clone(),
+        merged: merged_path.clone(),
+        labels,
+        options: compare.to_session(display.context),
+        started: SystemTime::now(),
+    };
+
     let (tx, rx) = mpsc::channel();
-    // PathBuf is Clone, so we can clone it
-    let f1_path = args.file1.clone();
-    let f2_path = args.file2.clone();
+    let f1_path = file1;
+    let f2_path = file2;
     let tx_clone = tx.clone();
 
-    thread::spawn(move || {
-        process_side_by_side(f1_path, f2_path, tx_clone);
+    thread::spawn(move || match base {
+        Some(base_path) => process_three_way(base_path, f1_path, f2_path, options, tx_clone),
+        None => process_side_by_side(f1_path, f2_path, options, tx_clone),
     });
 
-    let mut app = App {
-        state: AppState::Loading,
-        diff_ops: vec![],
-        op_row_counts: vec![],
-        file1: None,
-        file2: None,
-        scroll_offset: 0,
-        scroll_state: ScrollbarState::default(),
-        spinner_index: 0,
-        receiver: rx,
-        file1_name: f1_name,
-        file2_name: f2_name,
-        loading_log: "Initializing...".to_string(),
-        resolutions: vec![],
-        selected_op_index: None,
-    };
+    let mut app = App::new(rx, f1_name, f2_name);
+    app.base_name = base_name;
+    if let Some(path) = &merged_path {
+        app.output_path = path.clone();
+    }
+    app.context = display.context;
+    app.patch_labels = patch_labels;
+    app.setup = Some(setup);
+    if let Some((path, resolutions)) = resumed {
+        app.session_path = path;
+        app.resumed = Some(resolutions);
+    }
 
     let res = run_app(&mut stdout, &mut app).await;
 
@@ -280,21 +736,813 @@ async fn main() -> Result<()> {
         eprintln!("Error: {:?}", e);
     }
 
+    // As a git mergetool, report whether $MERGED was saved fully resolved
+    if merged_path.is_some() && !app.saved_all_resolved {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Runs the diff without a terminal UI and prints it to stdout.
+/// Returns whether the inputs differ.
+fn run_headless(file1: PathBuf, file2: PathBuf, format: Option<OutputFormat>, options: DiffOptions, labels: (String, String), context: usize) -> Result<bool> {
+    // A patch must describe the exact transformation, so comparison options don't apply
+    let options = if format == Some(OutputFormat::JsonPatch) { DiffOptions::default() } else { options };
+    if let Some(format @ (OutputFormat::Structural | OutputFormat::JsonPatch)) = format {
+        return run_structural(&file1, &file2, &options, format);
+    }
+
+    let (tx, rx) = mpsc::channel();
+    process_side_by_side(file1.clone(), file2.clone(), options, tx);
+
+    let result = rx
+        .into_iter()
+        .find_map(|event| match event {
+            AppEvent::Done(res) => Some(res),
+            AppEvent::Log(_) => None,
+        })
+        .context("Diff worker finished without a result")??;
+
+    if format == Some(OutputFormat::Json) {
+        check_not_truncated(result.changes_dropped)?;
+    }
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let differs = match format.unwrap_or(OutputFormat::Text) {
+        OutputFormat::Text => {
+            export::write_text(&mut out, &result.file1, &result.file2, &result.ops)?;
+            result.ops.iter().any(|op| !matches!(op, DiffOp::Equal { .. }))
+        }
+        OutputFormat::Unified => {
+            let raw = raw_patch_views(&result.file1, &result.file2, (&file1, &file2))?;
+            let (f1, f2, ops) = match &raw {
+                Some((f1, f2, ops)) => (f1, f2, ops),
+                None => (&result.file1, &result.file2, &result.ops),
+            };
+            export::write_unified(&mut out, f1, f2, ops, (&labels.0, &labels.1), context)?;
+            ops.iter().any(|op| !matches!(op, DiffOp::Equal { .. }))
+        }
+        OutputFormat::Html => {
+            let names = (file_label(&labels.0), file_label(&labels.1));
+            html::write_html(&mut out, &result.file1, &result.file2, &result.ops, names, context, &html_palette())?;
+            result.ops.iter().any(|op| !matches!(op, DiffOp::Equal { .. }))
+        }
+        OutputFormat::Json => {
+            let names = (file_label(&labels.0), file_label(&labels.1));
+            let report = export::to_json_report(&result.file1, &result.file2, &result.ops, result.changes.as_deref(), names);
+            serde_json::to_writer_pretty(&mut out, &report)?;
+            writeln!(out)?;
+            !report.identical
+        }
+        OutputFormat::Structural | OutputFormat::JsonPatch => unreachable!("handled by run_structural"),
+    };
+    out.flush()?;
+    Ok(differs)
+}
+
+/// `--format structural` and `json-patch`, which skip the line diff. With
+/// inputs too large to parse, structural output is printed as each change is
+/// found, so memory stays flat however many there are.
+fn run_structural(file1: &PathBuf, file2: &PathBuf, options: &DiffOptions, format: OutputFormat) -> Result<bool> {
+    let mut f1 = LazyDiffView::new(file1).context("Failed to read file 1")?;
+    let mut f2 = LazyDiffView::new(file2).context("Failed to read file 2")?;
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let changes = match (f1.value.take(), f2.value.take()) {
+        (Some(v1), Some(v2)) => structural::diff_values(&v1, &v2, options),
+        _ if streams_json(&f1, &f2) && format == OutputFormat::Structural => {
+            let mut differs = false;
+            stream::diff_streams(&f1.content, &f2.content, &mut |change| {
+                differs = true;
+                Ok(export::write_structural(&mut out, std::slice::from_ref(&change))?)
+            })?;
+            out.flush()?;
+            return Ok(differs);
+        }
+        // A patch needs every change before its first operation can be written
+        _ if streams_json(&f1, &f2) => {
+            let (changes, dropped) = collect_streamed(&f1, &f2)?;
+            check_not_truncated(dropped)?;
+            // Applying the patch would write the size summary into the document
+            if let Some(change) = changes.iter().find(|c| c.summarised) {
+                anyhow::bail!(
+                    "Cannot write a JSON Patch for these inputs: the value at {} is over 4 KB, and streaming only keeps its size",
+                    change.path
+                );
+            }
+            changes
+        }
+        _ => anyhow::bail!("This output format requires both inputs to be valid JSON"),
+    };
+
+    if format == OutputFormat::Structural {
+        export::write_structural(&mut out, &changes)?;
+    } else {
+        serde_json::to_writer_pretty(&mut out, &patch::to_json_patch(&changes))?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(!changes.is_empty())
+}
+
+/// Inputs above MAX_JSON_FORMAT_SIZE are diffed by streaming, which compares
+/// arrays by position only; refuse options it would silently ignore.
+fn check_streaming_options<'a>(paths: impl IntoIterator<Item = &'a PathBuf>, options: &DiffOptions) -> Result<()> {
+    if options.is_default() {
+        return Ok(());
+    }
+    for path in paths {
+        // Unreadable inputs are reported when they are loaded
+        if fs::metadata(path).is_ok_and(|m| m.len() > MAX_JSON_FORMAT_SIZE) {
+            anyhow::bail!(
+                "--ignore-order, --ignore-order-at and --array-key are not supported in streaming mode ({} is larger than {} MB)",
+                path.display(),
+                MAX_JSON_FORMAT_SIZE / (1024 * 1024)
+            );
+        }
+    }
+    Ok(())
+}
+
+// Whole-document formats can't be written from a partial change list
+fn check_not_truncated(dropped: usize) -> Result<()> {
+    if dropped > 0 {
+        anyhow::bail!(
+            "Too many structural changes to keep in memory ({} found, limit {}); use --format structural to stream them",
+            group_thousands(MAX_STREAMED_CHANGES + dropped),
+            group_thousands(MAX_STREAMED_CHANGES)
+        );
+    }
+    Ok(())
+}
+
+/// `apply` subcommand: patches `base` and writes the result to `output`.
+fn run_apply(base: &PathBuf, patch_path: &PathBuf, output: &str, merge_patch: bool) -> Result<()> {
+    let mut doc: Value = serde_json::from_str(&fs::read_to_string(base).context("Failed to read base file")?)
+        .context("Base file is not valid JSON")?;
+    let patch_doc: Value = serde_json::from_str(&fs::read_to_string(patch_path).context("Failed to read patch file")?)
+        .context("Patch file is not valid JSON")?;
+
+    if merge_patch || !patch_doc.is_array() {
+        patch::apply_merge_patch(&mut doc, &patch_doc);
+    } else if let Err(errors) = patch::apply_json_patch(&mut doc, &patch_doc) {
+        let report: Vec<String> = errors.iter().map(|e| format!("  {}", e)).collect();
+        anyhow::bail!("{} of {} patch operations failed:\n{}", errors.len(), patch_doc.as_array().map(|a| a.len()).unwrap_or(0), report.join("\n"));
+    }
+
+    let mut writer = create_output(output)?;
+    serde_json::to_writer_pretty(&mut writer, &doc)?;
+    writeln!(writer)?;
+    writer.flush()?;
     Ok(())
 }
 
 impl App {
+    fn new(receiver: mpsc::Receiver<AppEvent>, file1_name: String, file2_name: String) -> Self {
+        App {
+            state: AppState::Loading,
+            diff_ops: vec![],
+            op_row_counts: vec![],
+            file1: None,
+            file2: None,
+            scroll_offset: 0,
+            scroll_state: ScrollbarState::default(),
+            h_scroll: 0,
+            wrap: false,
+            wrap_skip: 0,
+            pane_width: 80,
+            wrap_starts: vec![],
+            fold: false,
+            context: 3,
+            expanded: HashSet::new(),
+            unified: false,
+            spinner_index: 0,
+            receiver,
+            file1_name,
+            file2_name,
+            base_name: None,
+            loading_log: "Initializing...".to_string(),
+            status: None,
+            inline_mode: inline::Granularity::Word,
+            inline_spans: HashMap::new(),
+            search: None,
+            search_rx: None,
+            search_cancel: Arc::new(AtomicBool::new(false)),
+            search_pos: None,
+            resolutions: vec![],
+            selected_op_index: None,
+            output_path: "merged_output.json".to_string(),
+            patch_labels: ("a/file1".to_string(), "b/file2".to_string()),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            setup: None,
+            session_path: "merge-session.json".to_string(),
+            resumed: None,
+            saved_all_resolved: false,
+            view_mode: ViewMode::Lines,
+            structural: None,
+            structural_dropped: 0,
+            structural_scroll: 0,
+        }
+    }
+
+    /// Sets resolutions as one undoable step named `label`. Returns how many changed.
+    fn resolve_many(&mut self, label: String, targets: Vec<(usize, Resolution)>) -> usize {
+        let mut changes = Vec::new();
+        for (i, resolution) in targets {
+            let Some(slot) = self.resolutions.get_mut(i) else { continue };
+            if *slot != resolution {
+                changes.push((i, std::mem::replace(slot, resolution.clone()), resolution));
+            }
+        }
+        let count = changes.len();
+        if count > 0 {
+            self.undo_stack.push(HistoryEntry { label, changes });
+            self.redo_stack.clear();
+        }
+        count
+    }
+
+    fn resolve(&mut self, i: usize, resolution: Resolution) {
+        let label = format!("{} on diff {}", resolution.label(), i + 1);
+        self.resolve_many(label, vec![(i, resolution)]);
+    }
+
+    /// Changed ops around `i` up to the nearest unchanged runs long enough
+    /// to fold, i.e. what folding shows as one block.
+    fn region_of(&self, i: usize) -> Range<usize> {
+        let long_gap = |j: usize| matches!(self.diff_ops[j], DiffOp::Equal { len, .. } if len > 2 * self.context + 1);
+        let mut start = i;
+        while start > 0 && !long_gap(start - 1) {
+            start -= 1;
+        }
+        let mut end = i + 1;
+        while end < self.diff_ops.len() && !long_gap(end) {
+            end += 1;
+        }
+        start..end
+    }
+
+    /// Whether a line of op `i` on either side lies within a value matching `pattern`.
+    fn op_matches_path(&self, i: usize, pattern: &PathPattern) -> bool {
+        let op = &self.diff_ops[i];
+        let matches = |view: &Option<Arc<LazyDiffView>>, lines: Range<usize>| {
+            let Some(view) = view.as_deref() else { return false };
+            let Some(paths) = &view.paths else { return false };
+            lines.filter_map(|line| paths.path_of(view, line)).any(|path| (0..=path.0.len()).any(|n| pattern.matches(&JsonPath(path.0[..n].to_vec()))))
+        };
+        matches(&self.file1, op.old_range()) || matches(&self.file2, op.new_range())
+    }
+
+    /// Ops a bulk action would change, with a description for the prompt and history.
+    fn bulk_plan(&self, target: &BulkTarget, resolution: Resolution) -> (String, Vec<(usize, Resolution)>) {
+        let changed = |i: &usize| !matches!(self.diff_ops[*i], DiffOp::Equal { .. });
+        let (ops, what): (Vec<usize>, String) = match target {
+            BulkTarget::Unresolved => ((0..self.diff_ops.len()).filter(changed).filter(|&i| self.resolutions[i] == Resolution::Unresolved).collect(), "unresolved".to_string()),
+            BulkTarget::Region(range) => (range.clone().filter(changed).collect(), "in this region".to_string()),
+            BulkTarget::Path(text, pattern) => ((0..self.diff_ops.len()).filter(changed).filter(|&i| self.op_matches_path(i, pattern)).collect(), format!("under {}", text)),
+        };
+        let targets: Vec<(usize, Resolution)> = ops.into_iter().filter(|&i| self.resolutions[i] != resolution).map(|i| (i, resolution.clone())).collect();
+        let plural = if targets.len() == 1 { "" } else { "s" };
+        let label = format!("{} on {} diff{} {}", resolution.label(), targets.len(), plural, what);
+        (label, targets)
+    }
+
+    /// u / Ctrl-r: steps back or forward through the history and shows the first op touched.
+    fn undo(&mut self, redo: bool) {
+        let (from, verb) = if redo { (&mut self.redo_stack, "Redid") } else { (&mut self.undo_stack, "Undid") };
+        let Some(entry) = from.pop() else {
+            self.status = Some(if redo { "Nothing to redo" } else { "Nothing to undo" }.to_string());
+            return;
+        };
+        for (i, before, after) in &entry.changes {
+            self.resolutions[*i] = if redo { after.clone() } else { before.clone() };
+        }
+        self.status = Some(format!("{}: {}", verb, entry.label));
+        if let Some(&(first, _, _)) = entry.changes.first() {
+            self.selected_op_index = Some(first);
+            self.reveal(first, 0);
+        }
+        if redo { self.undo_stack.push(entry) } else { self.redo_stack.push(entry) }
+    }
+
+    /// Changed ops that still have no resolution.
+    fn unresolved_count(&self) -> usize {
+        self.diff_ops
+            .iter()
+            .zip(&self.resolutions)
+            .filter(|(op, r)| !matches!(op, DiffOp::Equal { .. }) && **r == Resolution::Unresolved)
+            .count()
+    }
+
+    fn structural_len(&self) -> usize {
+        self.structural.as_ref().map(|c| c.len()).unwrap_or(0)
+    }
+
+    /// How a folded Equal op is shown: `head` lines, then one fold row
+    /// standing in for `hidden` lines, then the rest. None if not folded.
+    fn fold_of(&self, i: usize) -> Option<(usize, usize)> {
+        if !self.fold || self.expanded.contains(&i) {
+            return None;
+        }
+        let DiffOp::Equal { len, .. } = self.diff_ops[i] else { return None };
+        // No leading context before the first change, no trailing after the last
+        let head = if i > 0 { self.context } else { 0 };
+        let tail = if i + 1 < self.diff_ops.len() { self.context } else { 0 };
+        let hidden = len.saturating_sub(head + tail);
+        // Folding a single line would not save a row
+        (hidden > 1).then_some((head, hidden))
+    }
+
+    fn layout_rows(&self, i: usize) -> usize {
+        let rows = op_rows(&self.diff_ops[i], self.unified);
+        match self.fold_of(i) {
+            Some((_, hidden)) => rows - hidden + 1,
+            None => rows,
+        }
+    }
+
+    /// What row `offset` of op `i` shows: a line (offset within the op) or the fold.
+    fn row_content(&self, i: usize, offset: usize) -> RowContent {
+        match self.fold_of(i) {
+            Some((head, hidden)) if offset == head => RowContent::Fold(hidden),
+            Some((head, hidden)) if offset > head => RowContent::Line(offset - 1 + hidden),
+            _ => RowContent::Line(offset),
+        }
+    }
+
+    /// Row showing line `local` of op `i`, or its fold row if it is hidden.
+    fn row_of(&self, i: usize, local: usize) -> usize {
+        let offset = match self.fold_of(i) {
+            Some((head, hidden)) if local >= head + hidden => local + 1 - hidden,
+            Some((head, _)) if local >= head => head,
+            _ => local,
+        };
+        self.op_row_counts[i] + offset
+    }
+
+    /// Recomputes the row layout, e.g. after folding changed.
+    fn rebuild_rows(&mut self) {
+        let mut current_row = 0;
+        self.op_row_counts = Vec::with_capacity(self.diff_ops.len());
+        for i in 0..self.diff_ops.len() {
+            self.op_row_counts.push(current_row);
+            current_row += self.layout_rows(i);
+        }
+        self.wrap_starts.clear();
+        if self.wrap {
+            let mut visual = 0;
+            for i in 0..self.diff_ops.len() {
+                for offset in 0..self.layout_rows(i) {
+                    self.wrap_starts.push(visual);
+                    visual += match self.row_content(i, offset) {
+                        RowContent::Line(local) => {
+                            let (l, r) = op_row_lines(&self.diff_ops[i], local, self.unified);
+                            self.line_rows(l, r)
+                        }
+                        RowContent::Fold(_) => 1,
+                    };
+                }
+            }
+            self.wrap_starts.push(visual);
+            self.wrap_skip = self.wrap_skip.min(self.row_height(self.scroll_offset) - 1);
+        }
+        self.sync_scrollbar();
+    }
+
+    /// W: turns soft-wrap on or off, keeping the top line in place.
+    fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        self.wrap_skip = 0;
+        self.rebuild_rows();
+        self.status = Some(format!("Wrap: {}", if self.wrap { "on" } else { "off" }));
+    }
+
+    /// Visual rows taken by logical row `row`.
+    fn row_height(&self, row: usize) -> usize {
+        match (self.wrap_starts.get(row), self.wrap_starts.get(row + 1)) {
+            (Some(start), Some(end)) if self.wrap => end - start,
+            _ => 1,
+        }
+    }
+
+    /// Visual row the view is scrolled to, and the visual total.
+    fn visual_position(&self) -> (usize, usize) {
+        match self.wrap_starts.last() {
+            Some(&total) if self.wrap => (self.wrap_starts.get(self.scroll_offset).copied().unwrap_or(total) + self.wrap_skip, total),
+            _ => (self.scroll_offset, self.total_rows()),
+        }
+    }
+
+    fn sync_scrollbar(&mut self) {
+        let (position, total) = self.visual_position();
+        self.scroll_state = ScrollbarState::new(total).position(position);
+    }
+
+    /// Line `local` of op `i` at the top of the view, unfolding it if needed.
+    fn reveal(&mut self, i: usize, local: usize) {
+        if let Some((head, hidden)) = self.fold_of(i) {
+            if (head..head + hidden).contains(&local) {
+                self.expanded.insert(i);
+                self.rebuild_rows();
+            }
+        }
+        self.scroll_to(self.row_of(i, local));
+    }
+
+    /// z: folds or unfolds everything, keeping the top line in place.
+    fn toggle_fold(&mut self) {
+        let i = self.op_at_row(self.scroll_offset);
+        let local = match self.diff_ops.get(i) {
+            Some(_) => match self.row_content(i, self.scroll_offset - self.op_row_counts[i]) {
+                RowContent::Line(local) => local,
+                RowContent::Fold(_) => self.fold_of(i).map(|(head, _)| head).unwrap_or(0),
+            },
+            None => 0,
+        };
+        self.fold = !self.fold;
+        self.expanded.clear();
+        self.rebuild_rows();
+        if i < self.diff_ops.len() {
+            self.scroll_to(self.row_of(i, local));
+        }
+        self.status = Some(if self.fold { format!("Folding: {} context lines", self.context) } else { "Folding: off".to_string() });
+    }
+
+    /// v: switches between split and unified layout, keeping the top line in place.
+    fn toggle_unified(&mut self) {
+        let (l, r) = self.row_lines(self.scroll_offset);
+        let i = self.op_at_row(self.scroll_offset);
+        self.unified = !self.unified;
+        self.rebuild_rows();
+        let anchor = match (l, r) {
+            (Some(l), _) => self.op_of_line(true, l),
+            (None, Some(r)) => self.op_of_line(false, r),
+            // Fold row, which is laid out the same either way
+            (None, None) => self.fold_of(i).map(|(head, _)| (i, head)),
+        };
+        if let Some((op, offset)) = anchor {
+            self.scroll_to(self.row_of(op, offset));
+        }
+        // Hits are rows of the old layout
+        self.status = Some(if self.unified { "Unified view".to_string() } else { "Split view".to_string() });
+        if let Some(query) = self.search.as_ref().map(|s| s.query.clone()) {
+            self.start_search(query);
+        }
+    }
+
+    /// e: expands the first fold row within `height` rows of the top.
+    fn expand_visible_fold(&mut self, height: usize) {
+        let mut i = self.op_at_row(self.scroll_offset);
+        while i < self.diff_ops.len() && self.op_row_counts[i] < self.scroll_offset + height {
+            if let Some((head, _)) = self.fold_of(i) {
+                if self.op_row_counts[i] + head >= self.scroll_offset {
+                    self.expanded.insert(i);
+                    self.rebuild_rows();
+                    return;
+                }
+            }
+            i += 1;
+        }
+    }
+
+    /// Index of the op shown on a logical row.
+    fn op_at_row(&self, row: usize) -> usize {
+        match self.op_row_counts.binary_search(&row) {
+            Ok(i) => i,
+            Err(i) => i.saturating_sub(1),
+        }
+    }
+
+    /// Line indices shown on each side of a logical row.
+    fn row_lines(&self, row: usize) -> (Option<usize>, Option<usize>) {
+        let i = self.op_at_row(row);
+        match self.diff_ops.get(i) {
+            Some(op) => match self.row_content(i, row - self.op_row_counts[i]) {
+                RowContent::Line(local) => op_row_lines(op, local, self.unified),
+                RowContent::Fold(_) => (None, None),
+            },
+            None => (None, None),
+        }
+    }
+
+    /// Line on each side the header path describes: the selected op while it
+    /// is on screen, else the top visible row. A gap uses the line after it.
+    fn focus_lines(&self, view_height: usize) -> (usize, usize) {
+        let on_screen = |row: usize| row >= self.scroll_offset && row < self.scroll_offset + view_height;
+        let (i, offset) = match self.selected_op_index.filter(|&i| on_screen(self.op_row_counts[i])) {
+            Some(i) => (i, 0),
+            None => {
+                let i = self.op_at_row(self.scroll_offset);
+                (i, self.scroll_offset - self.op_row_counts.get(i).copied().unwrap_or(0))
+            }
+        };
+        let Some(op) = self.diff_ops.get(i) else { return (0, 0) };
+        let local = match self.row_content(i, offset) {
+            RowContent::Line(local) => local,
+            RowContent::Fold(_) => self.fold_of(i).map(|(head, _)| head).unwrap_or(0),
+        };
+        let (l, r) = op_row_lines(op, local, self.unified);
+        (l.unwrap_or(op.old_range().start), r.unwrap_or(op.new_range().start))
+    }
+
+    /// Visual rows taken by a pair of lines (always 1 unless wrapping).
+    fn line_rows(&self, left: Option<usize>, right: Option<usize>) -> usize {
+        if !self.wrap {
+            return 1;
+        }
+        let rows = |view: &Option<Arc<LazyDiffView>>, idx: Option<usize>| {
+            idx.and_then(|i| view.as_ref()?.get_line(i)).map(|l| wrap_breaks(l, self.pane_width).len()).unwrap_or(1)
+        };
+        rows(&self.file1, left).max(rows(&self.file2, right))
+    }
+
+    fn scroll_to(&mut self, row: usize) {
+        self.scroll_offset = row;
+        self.wrap_skip = 0;
+        self.sync_scrollbar();
+    }
+
+    /// End: the last visual row at the top.
+    fn scroll_to_end(&mut self) {
+        let last = self.total_rows().saturating_sub(1);
+        self.scroll_to(last);
+        self.wrap_skip = self.row_height(last) - 1;
+        self.sync_scrollbar();
+    }
+
+    /// Scrolls by visual rows, stepping through the wrapped rows of each line.
+    fn scroll_down(&mut self, n: usize) {
+        let last = self.total_rows().saturating_sub(1);
+        for _ in 0..n {
+            if self.wrap_skip + 1 < self.row_height(self.scroll_offset) {
+                self.wrap_skip += 1;
+            } else if self.scroll_offset < last {
+                self.scroll_offset += 1;
+                self.wrap_skip = 0;
+            } else {
+                break;
+            }
+        }
+        self.sync_scrollbar();
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        for _ in 0..n {
+            if self.wrap_skip > 0 {
+                self.wrap_skip -= 1;
+            } else if self.scroll_offset > 0 {
+                self.scroll_offset -= 1;
+                self.wrap_skip = self.row_height(self.scroll_offset) - 1;
+            } else {
+                break;
+            }
+        }
+        self.sync_scrollbar();
+    }
+
+    /// Starts a background scan for `query`, cancelling any running one.
+    fn start_search(&mut self, query: String) {
+        let (Some(f1), Some(f2)) = (self.file1.clone(), self.file2.clone()) else { return };
+        self.clear_search();
+        let (tx, rx) = mpsc::channel();
+        let cancel = self.search_cancel.clone();
+        let ops = self.diff_ops.clone();
+        let unified = self.unified;
+        self.status = Some(format!("Searching for \"{}\"...", query));
+        thread::spawn(move || {
+            let _ = tx.send(search::scan(&f1, &f2, &ops, unified, &query, &cancel));
+        });
+        self.search_rx = Some(rx);
+    }
+
+    fn clear_search(&mut self) {
+        self.search_cancel.store(true, Ordering::Relaxed);
+        self.search_cancel = Arc::new(AtomicBool::new(false));
+        self.search = None;
+        self.search_rx = None;
+        self.search_pos = None;
+        self.status = None;
+    }
+
+    /// Picks up a finished scan and jumps to the first hit at or below the view.
+    fn poll_search(&mut self) {
+        let Some(rx) = &self.search_rx else { return };
+        let Ok(results) = rx.try_recv() else { return };
+        self.search_rx = None;
+        let Some(results) = results else { return };
+        self.status = Some(format!(
+            "\"{}\": {} matching rows ({} left, {} right)",
+            results.query,
+            results.hits.len(),
+            results.left_lines,
+            results.right_lines
+        ));
+        let first = results.hits.iter().position(|h| self.hit_row(*h) >= self.scroll_offset);
+        self.search = Some(results);
+        if let Some(k) = first {
+            self.jump_to_hit(k);
+        }
+    }
+
+    /// Op containing `line` of one side, and the row offset within it showing that line.
+    fn op_of_line(&self, left: bool, line: usize) -> Option<(usize, usize)> {
+        let range = |op: &DiffOp| if left { op.old_range() } else { op.new_range() };
+        let i = self.diff_ops.partition_point(|op| range(op).end <= line);
+        let op = self.diff_ops.get(i)?;
+        let offset = line - range(op).start;
+        // Unified Replace rows list the added lines after the removed ones
+        match *op {
+            DiffOp::Replace { old_len, .. } if self.unified && !left => Some((i, old_len + offset)),
+            _ => Some((i, offset)),
+        }
+    }
+
+    /// Scrolls to the line where `query` (JSONPath or JSON Pointer) starts,
+    /// preferring the left side.
+    fn goto_path(&mut self, query: &str) {
+        let path: JsonPath = match query.parse() {
+            Ok(path) => path,
+            Err(e) => {
+                self.status = Some(format!("Invalid path: {}", e));
+                return;
+            }
+        };
+        let find = |view: &Option<Arc<LazyDiffView>>| view.as_ref().and_then(|v| v.paths.as_ref()?.resolve(v, &path.0));
+        let (left, right) = (find(&self.file1), find(&self.file2));
+
+        let target = match (left, right) {
+            (Some(l), _) => self.op_of_line(true, l),
+            (None, Some(r)) => self.op_of_line(false, r),
+            (None, None) => None,
+        };
+        let Some((op, local)) = target else {
+            self.status = Some(format!("{} not found", path));
+            return;
+        };
+        self.reveal(op, local);
+        if !matches!(self.diff_ops[op], DiffOp::Equal { .. }) {
+            self.selected_op_index = Some(op);
+        }
+        let line = |l: Option<usize>| l.map(|l| (l + 1).to_string()).unwrap_or_else(|| "-".to_string());
+        self.status = Some(format!("{}: line {} | {}", path, line(left), line(right)));
+    }
+
+    fn hit_row(&self, hit: search::SearchHit) -> usize {
+        self.row_of(hit.op, hit.offset)
+    }
+
+    // Merged output is only checked as JSON when both inputs look like JSON
+    fn merges_json(&self) -> bool {
+        [&self.file1, &self.file2].iter().all(|f| f.as_ref().is_some_and(|v| stream::looks_like_json(&v.content[..])))
+    }
+
+    /// Op and row of the input line a merged-output syntax error comes from.
+    fn merge_error_row(&self, error: &validate::SyntaxError) -> Option<(usize, usize)> {
+        let i = error.tag;
+        let op = self.diff_ops.get(i)?;
+        let resolution = self.resolutions.get(i).unwrap_or(&Resolution::Unresolved);
+        let mut line = error.tag_line;
+        for piece in merged_pieces(op, resolution) {
+            let (left, lines) = match piece {
+                Piece::Left(lines) => (true, lines),
+                Piece::Right(lines) => (false, lines),
+                Piece::Text(_) => break,
+            };
+            let view = if left { &self.file1 } else { &self.file2 };
+            let bytes = view.as_ref()?.line_bytes(lines.clone());
+            let newlines = bytes.iter().filter(|&&b| b == b'\n').count();
+            // An unterminated last line runs on into the next piece
+            if line < newlines || (line == newlines && !bytes.is_empty() && !bytes.ends_with(b"\n")) {
+                return self.op_of_line(left, lines.start + line);
+            }
+            line -= newlines;
+        }
+        Some((i, 0))
+    }
+
+    /// J: checks the merged output as JSON and jumps to the first syntax error.
+    fn check_merged(&mut self) {
+        if !self.merges_json() {
+            self.status = Some("Inputs are not JSON; nothing to check".to_string());
+            return;
+        }
+        let error = match write_merged(self, &mut io::sink()) {
+            Ok(Some(error)) => error,
+            Ok(None) => {
+                self.status = Some("Merged output is valid JSON".to_string());
+                return;
+            }
+            Err(e) => {
+                self.status = Some(format!("Check failed: {:#}", e));
+                return;
+            }
+        };
+        if let Some((op, local)) = self.merge_error_row(&error) {
+            self.reveal(op, local);
+            if !matches!(self.diff_ops[op], DiffOp::Equal { .. }) {
+                self.selected_op_index = Some(op);
+            }
+        }
+        self.status = Some(format!("Invalid merged JSON: {} (diff {})", error, error.tag + 1));
+    }
+
+    fn jump_to_hit(&mut self, k: usize) {
+        let Some(results) = &self.search else { return };
+        let hit = results.hits[k];
+        let total = results.hits.len();
+        self.reveal(hit.op, hit.offset);
+        if !matches!(self.diff_ops[hit.op], DiffOp::Equal { .. }) {
+            self.selected_op_index = Some(hit.op);
+        }
+        self.search_pos = Some(k);
+        self.status = Some(format!("Match {}/{}", k + 1, total));
+    }
+
+    /// n / N while searching: next or previous hit, wrapping around.
+    fn step_search(&mut self, forward: bool) {
+        let Some(results) = &self.search else { return };
+        let total = results.hits.len();
+        if total == 0 {
+            return;
+        }
+        let k = match self.search_pos {
+            Some(k) if forward => (k + 1) % total,
+            Some(k) => (k + total - 1) % total,
+            None => 0,
+        };
+        self.jump_to_hit(k);
+    }
+
+    /// w: the next intra-line granularity. Cached spans were found with the
+    /// old one, so they go.
+    fn cycle_inline_mode(&mut self) {
+        self.inline_mode = self.inline_mode.next();
+        self.inline_spans.clear();
+        self.status = Some(format!("Inline highlight: {}", self.inline_mode.label()));
+    }
+
+    /// n / p (and N when not searching): selects the next or previous
+    /// non-equal op and scrolls to it.
+    fn step_conflict(&mut self, forward: bool) {
+        let found = match self.selected_op_index {
+            Some(i) if forward => (i + 1..self.diff_ops.len()).find(|&j| !matches!(self.diff_ops[j], DiffOp::Equal { .. })),
+            Some(i) => (0..i).rev().find(|&j| !matches!(self.diff_ops[j], DiffOp::Equal { .. })),
+            None if forward => self.diff_ops.iter().position(|op| !matches!(op, DiffOp::Equal { .. })),
+            None => self.diff_ops.iter().rposition(|op| !matches!(op, DiffOp::Equal { .. })),
+        };
+        if let Some(i) = found {
+            self.selected_op_index = Some(i);
+            self.scroll_to(self.op_row_counts[i]);
+        }
+    }
+
+    /// Text the editor starts from: an earlier edit, or both sides of the
+    /// hunk between git-style conflict markers.
+    fn edit_seed(&self, i: usize) -> String {
+        if let Some(Resolution::Edit(text)) = self.resolutions.get(i) {
+            return text.clone();
+        }
+        let op = &self.diff_ops[i];
+        let side = |view: &Option<Arc<LazyDiffView>>, range: Range<usize>| {
+            let mut text = view.as_ref().map(|v| String::from_utf8_lossy(v.line_bytes(range)).into_owned()).unwrap_or_default();
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text
+        };
+        format!(
+            "<<<<<<< {}\n{}=======\n{}>>>>>>> {}\n",
+            self.file1_name,
+            side(&self.file1, op.old_range()),
+            side(&self.file2, op.new_range()),
+            self.file2_name
+        )
+    }
+
+    /// Stores an edit as op `i`'s resolution. Editors add a final newline;
+    /// it is kept only if the lines it replaces had one, so the result joins
+    /// the following lines the same way.
+    fn apply_edit(&mut self, i: usize, mut text: String) {
+        let op = &self.diff_ops[i];
+        let (view, range) = if op.old_range().is_empty() { (&self.file2, op.new_range()) } else { (&self.file1, op.old_range()) };
+        let ends_line = view.as_ref().is_some_and(|v| v.line_bytes(range).ends_with(b"\n"));
+        if ends_line && !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        } else if !ends_line && text.ends_with('\n') {
+            text.pop();
+        }
+        self.resolve(i, Resolution::Edit(text));
+        self.status = Some(format!("Diff {} resolved with edited text", i + 1));
+    }
+
     fn total_rows(&self) -> usize {
         if self.diff_ops.is_empty() { return 0; }
-        let last_op = self.diff_ops.last().unwrap();
         let last_start = self.op_row_counts.last().unwrap_or(&0);
-        let len = match last_op {
-            DiffOp::Equal { len, .. } => *len,
-            DiffOp::Delete { old_len, .. } => *old_len,
-            DiffOp::Insert { new_len, .. } => *new_len,
-            DiffOp::Replace { old_len, new_len, .. } => std::cmp::max(*old_len, *new_len),
-        };
-        last_start + len
+        last_start + self.layout_rows(self.diff_ops.len() - 1)
     }
 }
 
@@ -314,32 +1562,37 @@ async fn run_app(terminal: &mut io::Stdout, app: &mut App) -> Result<()> {
                     }
                     AppEvent::Done(result) => {
                         match result {
-                            Ok((f1, f2, ops)) => {
-                                app.file1 = Some(f1);
-                                app.file2 = Some(f2);
-                                app.diff_ops = ops;
+                            Ok(result) => {
+                                app.file1 = Some(Arc::new(result.file1));
+                                app.file2 = Some(Arc::new(result.file2));
+                                app.diff_ops = result.ops;
+                                app.inline_spans.clear();
+                                app.structural = result.changes;
+                                app.structural_dropped = result.changes_dropped;
+                                app.structural_scroll = 0;
                                 
-                                // Calculate cumulative row counts
-                                let mut current_row = 0;
-                                app.op_row_counts = Vec::with_capacity(app.diff_ops.len());
-                                for op in &app.diff_ops {
-                                    app.op_row_counts.push(current_row);
-                                    let rows = match op {
-                                        DiffOp::Equal { len, .. } => *len,
-                                        DiffOp::Delete { old_len, .. } => *old_len,
-                                        DiffOp::Insert { new_len, .. } => *new_len,
-                                        DiffOp::Replace { old_len, new_len, .. } => std::cmp::max(*old_len, *new_len),
-                                    };
-                                    current_row += rows;
-                                }
-
-                                app.scroll_state = ScrollbarState::new(current_row);
+                                app.rebuild_rows();
                                 
-                                // Initialize resolutions
-                                app.resolutions = vec![Resolution::Unresolved; app.diff_ops.len()];
+                                // Initialize resolutions (a three-way merge arrives pre-resolved)
+                                app.resolutions = result.resolutions.unwrap_or_else(|| vec![Resolution::Unresolved; app.diff_ops.len()]);
                                 app.selected_op_index = None;
                                 
                                 app.state = AppState::Done;
+
+                                // Inputs were verified, so the same ops come back
+                                if let Some(resolutions) = app.resumed.take() {
+                                    if resolutions.len() == app.diff_ops.len() {
+                                        app.resolutions = resolutions;
+                                        app.status = Some(format!("Resumed {} ({} unresolved)", app.session_path, app.unresolved_count()));
+                                    } else {
+                                        app.state = AppState::Error(format!(
+                                            "{} has {} resolutions but the diff has {} ops",
+                                            app.session_path,
+                                            resolutions.len(),
+                                            app.diff_ops.len()
+                                        ));
+                                    }
+                                }
                             }
                             Err(e) => app.state = AppState::Error(e.to_string()),
                         }
@@ -348,6 +1601,8 @@ async fn run_app(terminal: &mut io::Stdout, app: &mut App) -> Result<()> {
             }
         }
 
+        app.poll_search();
+
         // Poll faster for smoother spinner animation
         if event::poll(Duration::from_millis(50))? {
             if let Event::Key(key) = event::read()? {
@@ -359,8 +1614,16 @@ async fn run_app(terminal: &mut io::Stdout, app: &mut App) -> Result<()> {
                                 KeyCode::Enter => {
                                     let path = input.clone();
                                     app.state = AppState::Done; // Restore state first
-                                    if let Err(_e) = save_merged_output(app, &path) {
-                                        
+                                    match save_merged_output(app, &path) {
+                                        Ok(error) => {
+                                            let unresolved = app.unresolved_count();
+                                            app.saved_all_resolved = unresolved == 0 && error.is_none();
+                                            app.status = Some(match error {
+                                                Some(e) => format!("Saved {} ({} unresolved), but it is not valid JSON: {} (diff {}). J: jump there", path, unresolved, e, e.tag + 1),
+                                                None => format!("Saved {} ({} unresolved)", path, unresolved),
+                                            });
+                                        }
+                                        Err(e) => app.status = Some(format!("Save failed: {:#}", e)),
                                     }
                                 }
                                 KeyCode::Esc => {
@@ -375,100 +1638,218 @@ async fn run_app(terminal: &mut io::Stdout, app: &mut App) -> Result<()> {
                                 _ => {}
                              }
                         }
-                        AppState::Done => {
-                            match key.code {
-                                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
-                                KeyCode::Char('n') => {
-                                    let start_idx = if let Some(i) = app.selected_op_index { i + 1 } else { 0 };
-                                    for i in start_idx..app.diff_ops.len() {
-                                        if !matches!(app.diff_ops[i], DiffOp::Equal { .. }) {
-                                            app.selected_op_index = Some(i);
-                                            app.scroll_offset = app.op_row_counts[i];
-                                            app.scroll_state = app.scroll_state.position(app.scroll_offset);
-                                            break;
-                                        }
+                        AppState::SavingSession(input) => match key.code {
+                            KeyCode::Enter => {
+                                let path = input.clone();
+                                app.state = AppState::Done;
+                                app.status = Some(match save_session(app, &path) {
+                                    Ok(()) => {
+                                        app.session_path = path.clone();
+                                        format!("Saved session {} (resume with --resume {})", path, path)
                                     }
+                                    Err(e) => format!("Session save failed: {:#}", e),
+                                });
+                            }
+                            KeyCode::Esc => app.state = AppState::Done,
+                            code => edit_input(input, code),
+                        },
+                        AppState::Searching(input) => match key.code {
+                            KeyCode::Enter => {
+                                let query = input.clone();
+                                app.state = AppState::Done;
+                                if !query.is_empty() {
+                                    app.start_search(query);
                                 }
-                                KeyCode::Char('p') => {
-                                    let start_idx = if let Some(i) = app.selected_op_index { i.saturating_sub(1) } else { 0 };
-                                    for i in (0..=start_idx).rev() {
-                                        if !matches!(app.diff_ops[i], DiffOp::Equal { .. }) {
-                                            app.selected_op_index = Some(i);
-                                            app.scroll_offset = app.op_row_counts[i];
-                                            app.scroll_state = app.scroll_state.position(app.scroll_offset);
-                                            break;
-                                        }
+                            }
+                            KeyCode::Esc => app.state = AppState::Done,
+                            code => edit_input(input, code),
+                        },
+                        AppState::Exporting(input) => match key.code {
+                            KeyCode::Enter => {
+                                let path = input.clone();
+                                app.state = AppState::Done;
+                                app.status = Some(match export_patch(app, &path) {
+                                    Ok(()) => format!("Exported {}", path),
+                                    Err(e) => format!("Export failed: {:#}", e),
+                                });
+                            }
+                            KeyCode::Esc => app.state = AppState::Done,
+                            code => edit_input(input, code),
+                        },
+                        AppState::BulkMenu(None) => match key.code {
+                            KeyCode::Char('a') => app.state = AppState::BulkMenu(Some(BulkTarget::Unresolved)),
+                            KeyCode::Char('r') => {
+                                let i = app.selected_op_index.unwrap_or_else(|| app.op_at_row(app.scroll_offset));
+                                app.state = if i < app.diff_ops.len() { AppState::BulkMenu(Some(BulkTarget::Region(app.region_of(i)))) } else { AppState::Done };
+                            }
+                            KeyCode::Char('p') => app.state = AppState::BulkPath(String::new()),
+                            _ => app.state = AppState::Done,
+                        },
+                        AppState::BulkMenu(Some(target)) => {
+                            let resolution = match key.code {
+                                KeyCode::Char('1') | KeyCode::Left => Some(Resolution::PickLeft),
+                                KeyCode::Char('2') | KeyCode::Right => Some(Resolution::PickRight),
+                                KeyCode::Char('3') => Some(Resolution::PickBoth),
+                                _ => None,
+                            };
+                            let target = target.clone();
+                            app.state = match resolution {
+                                Some(resolution) => {
+                                    let (label, targets) = app.bulk_plan(&target, resolution);
+                                    if targets.is_empty() {
+                                        app.status = Some(format!("{}: nothing to change", label));
+                                        AppState::Done
+                                    } else {
+                                        AppState::BulkConfirm(label, targets)
+                                    }
+                                }
+                                None => AppState::Done,
+                            };
+                        }
+                        AppState::BulkPath(input) => match key.code {
+                            KeyCode::Enter => {
+                                let text = input.trim().to_string();
+                                app.state = match text.parse::<PathPattern>() {
+                                    Ok(pattern) => AppState::BulkMenu(Some(BulkTarget::Path(text, pattern))),
+                                    Err(e) => {
+                                        app.status = Some(format!("Invalid pattern: {}", e));
+                                        AppState::Done
                                     }
+                                };
+                            }
+                            KeyCode::Esc => app.state = AppState::Done,
+                            code => edit_input(input, code),
+                        },
+                        AppState::BulkConfirm(label, targets) => {
+                            if matches!(key.code, KeyCode::Char('y') | KeyCode::Enter) {
+                                let (label, targets) = (label.clone(), std::mem::take(targets));
+                                app.resolve_many(label.clone(), targets);
+                                app.status = Some(label);
+                            }
+                            app.state = AppState::Done;
+                        }
+                        AppState::Goto(input) => match key.code {
+                            KeyCode::Enter => {
+                                let query = input.clone();
+                                app.state = AppState::Done;
+                                app.goto_path(&query);
+                            }
+                            KeyCode::Esc => app.state = AppState::Done,
+                            code => edit_input(input, code),
+                        },
+                        AppState::Done if app.view_mode == ViewMode::Structural => {
+                            let last = app.structural_len().saturating_sub(1);
+                            match key.code {
+                                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                                KeyCode::Tab => app.view_mode = ViewMode::Lines,
+                                KeyCode::Down | KeyCode::Char('j') => {
+                                    app.structural_scroll = (app.structural_scroll + 1).min(last);
+                                }
+                                KeyCode::Up | KeyCode::Char('k') => {
+                                    app.structural_scroll = app.structural_scroll.saturating_sub(1);
+                                }
+                                KeyCode::PageDown => {
+                                    let height = t.size()?.height as usize;
+                                    app.structural_scroll = (app.structural_scroll + height).min(last);
+                                }
+                                KeyCode::PageUp => {
+                                    let height = t.size()?.height as usize;
+                                    app.structural_scroll = app.structural_scroll.saturating_sub(height);
+                                }
+                                KeyCode::Home => app.structural_scroll = 0,
+                                KeyCode::End => app.structural_scroll = last,
+                                _ => {}
+                            }
+                        }
+                        AppState::Done => {
+                            let searching = app.search.is_some() || app.search_rx.is_some();
+                            match key.code {
+                                KeyCode::Esc if searching => app.clear_search(),
+                                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                                KeyCode::Char('/') => app.state = AppState::Searching(String::new()),
+                                KeyCode::Char('g') => app.state = AppState::Goto(String::new()),
+                                KeyCode::Char('n') if searching => app.step_search(true),
+                                KeyCode::Char('N') if searching => app.step_search(false),
+                                KeyCode::Tab => app.view_mode = ViewMode::Structural,
+                                KeyCode::Char('w') => {
+                                    app.cycle_inline_mode();
                                 }
+                                KeyCode::Char('n') => app.step_conflict(true),
+                                KeyCode::Char('p') | KeyCode::Char('N') => app.step_conflict(false),
                                 KeyCode::Char('1') | KeyCode::Left => {
                                      if let Some(idx) = app.selected_op_index {
-                                         if idx < app.resolutions.len() {
-                                             app.resolutions[idx] = Resolution::PickLeft;
-                                         }
+                                         app.resolve(idx, Resolution::PickLeft);
                                      } else {
-                                        let step = 10;
-                                        app.scroll_offset = app.scroll_offset.saturating_sub(step);
-                                        app.scroll_state = app.scroll_state.position(app.scroll_offset);
+                                        app.scroll_up(10);
                                      }
                                 }
                                 KeyCode::Char('2') | KeyCode::Right => {
                                      if let Some(idx) = app.selected_op_index {
-                                         if idx < app.resolutions.len() {
-                                             app.resolutions[idx] = Resolution::PickRight;
-                                         }
+                                         app.resolve(idx, Resolution::PickRight);
                                      } else {
-                                        let step = 10;
-                                        app.scroll_offset = (app.scroll_offset + step).min(app.total_rows().saturating_sub(1));
-                                        app.scroll_state = app.scroll_state.position(app.scroll_offset);
+                                        app.scroll_down(10);
                                      }
                                 }
                                 KeyCode::Char('3') => {
                                      if let Some(idx) = app.selected_op_index {
-                                         if idx < app.resolutions.len() {
-                                             app.resolutions[idx] = Resolution::PickBoth;
-                                         }
+                                         app.resolve(idx, Resolution::PickBoth);
                                      }
                                 }
+                                KeyCode::Char('4') => {
+                                    if let Some(idx) = app.selected_op_index {
+                                        let seed = app.edit_seed(idx);
+                                        // Hand the terminal to the editor while it runs
+                                        disable_raw_mode()?;
+                                        execute!(t.backend_mut(), LeaveAlternateScreen)?;
+                                        let edited = edit_in_editor(&seed);
+                                        enable_raw_mode()?;
+                                        execute!(t.backend_mut(), EnterAlternateScreen)?;
+                                        t.clear()?;
+                                        match edited {
+                                            Ok(Some(text)) => app.apply_edit(idx, text),
+                                            Ok(None) => app.status = Some("Edit unchanged, resolution kept".to_string()),
+                                            Err(e) => app.status = Some(format!("Edit failed: {:#}", e)),
+                                        }
+                                    }
+                                }
                                 KeyCode::Backspace => {
                                      if let Some(idx) = app.selected_op_index {
-                                         if idx < app.resolutions.len() {
-                                             app.resolutions[idx] = Resolution::Unresolved;
-                                         }
+                                         app.resolve(idx, Resolution::Unresolved);
                                      }
                                 }
+                                KeyCode::Char('b') => app.state = AppState::BulkMenu(None),
+                                KeyCode::Char('u') => app.undo(false),
+                                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => app.undo(true),
                                 KeyCode::Char('s') => {
-                                    app.state = AppState::Saving("merged_output.json".to_string());
+                                    app.state = AppState::Saving(app.output_path.clone());
                                 }
-                                KeyCode::Down | KeyCode::Char('j') => {
-                                    if app.scroll_offset < app.total_rows().saturating_sub(1) {
-                                        app.scroll_offset += 1;
-                                        app.scroll_state = app.scroll_state.position(app.scroll_offset);
-                                    }
-                                }
-                                KeyCode::Up | KeyCode::Char('k') => {
-                                    if app.scroll_offset > 0 {
-                                        app.scroll_offset -= 1;
-                                        app.scroll_state = app.scroll_state.position(app.scroll_offset);
-                                    }
+                                KeyCode::Char('S') => app.state = AppState::SavingSession(app.session_path.clone()),
+                                KeyCode::Char('J') => app.check_merged(),
+                                KeyCode::Char('x') => {
+                                    app.state = AppState::Exporting("diff.patch".to_string());
                                 }
+                                KeyCode::Down | KeyCode::Char('j') => app.scroll_down(1),
+                                KeyCode::Up | KeyCode::Char('k') => app.scroll_up(1),
                                 KeyCode::PageDown => {
                                     let height = t.size()?.height as usize;
-                                    app.scroll_offset = (app.scroll_offset + height).min(app.total_rows().saturating_sub(1));
-                                    app.scroll_state = app.scroll_state.position(app.scroll_offset);
+                                    app.scroll_down(height);
                                 }
                                 KeyCode::PageUp => {
                                     let height = t.size()?.height as usize;
-                                    app.scroll_offset = app.scroll_offset.saturating_sub(height);
-                                    app.scroll_state = app.scroll_state.position(app.scroll_offset);
-                                }
-                                KeyCode::Home => {
-                                    app.scroll_offset = 0;
-                                    app.scroll_state = app.scroll_state.position(0);
+                                    app.scroll_up(height);
                                 }
-                                KeyCode::End => {
-                                    app.scroll_offset = app.total_rows().saturating_sub(1);
-                                    app.scroll_state = app.scroll_state.position(app.scroll_offset);
+                                KeyCode::Char('l') if !app.wrap => app.h_scroll += H_SCROLL_STEP,
+                                KeyCode::Char('h') if !app.wrap => app.h_scroll = app.h_scroll.saturating_sub(H_SCROLL_STEP),
+                                KeyCode::Char('0') => app.h_scroll = 0,
+                                KeyCode::Char('z') => app.toggle_fold(),
+                                KeyCode::Char('v') => app.toggle_unified(),
+                                KeyCode::Char('e') => {
+                                    let height = t.size()?.height as usize;
+                                    app.expand_visible_fold(height);
                                 }
+                                KeyCode::Char('W') => app.toggle_wrap(),
+                                KeyCode::Home => app.scroll_to(0),
+                                KeyCode::End => app.scroll_to_end(),
                                 _ => {}
                             }
                         }
@@ -480,6 +1861,17 @@ async fn run_app(terminal: &mut io::Stdout, app: &mut App) -> Result<()> {
     }
 }
 
+// Shared line editing for the prompt popups
+fn edit_input(input: &mut String, code: KeyCode) {
+    match code {
+        KeyCode::Backspace => {
+            input.pop();
+        }
+        KeyCode::Char(c) => input.push(c),
+        _ => {}
+    }
+}
+
 fn ui(f: &mut Frame, app: &mut App) {
     let size = f.area();
     
@@ -490,16 +1882,59 @@ fn ui(f: &mut Frame, app: &mut App) {
     match &app.state {
         AppState::Loading => draw_loading(f, app, size),
         AppState::Error(msg) => draw_error(f, msg, size),
+        AppState::Done if app.view_mode == ViewMode::Structural => draw_structural_view(f, app, size),
         AppState::Done => draw_diff_view(f, app, size),
         AppState::Saving(input) => {
             let input_clone = input.clone();
             draw_diff_view(f, app, size); // Draw background
-            draw_saving_popup(f, &input_clone, size);
+            draw_input_popup(f, " Save As ", &input_clone, size);
+        }
+        AppState::SavingSession(input) => {
+            let input_clone = input.clone();
+            draw_diff_view(f, app, size);
+            draw_input_popup(f, " Save Session As ", &input_clone, size);
+        }
+        AppState::Exporting(input) => {
+            let input_clone = input.clone();
+            draw_diff_view(f, app, size);
+            draw_input_popup(f, " Export (.patch or .html) ", &input_clone, size);
+        }
+        AppState::BulkMenu(target) => {
+            let text = match target {
+                None => "a: all unresolved   r: this region   p: JSON path pattern",
+                Some(_) => "1: Pick Left   2: Pick Right   3: Pick Both",
+            };
+            draw_diff_view(f, app, size);
+            draw_popup(f, " Bulk Resolve ", text, " [Esc]: Cancel ", size);
+        }
+        AppState::BulkPath(input) => {
+            let input_clone = input.clone();
+            draw_diff_view(f, app, size);
+            draw_input_popup(f, " Bulk: JSON path pattern (e.g. users[*].roles) ", &input_clone, size);
+        }
+        AppState::BulkConfirm(label, _) => {
+            let text = format!("{}?", label);
+            draw_diff_view(f, app, size);
+            draw_popup(f, " Bulk Resolve ", &text, " [y/Enter]: Apply or any other key: Cancel ", size);
+        }
+        AppState::Searching(input) => {
+            let input_clone = input.clone();
+            draw_diff_view(f, app, size);
+            draw_input_popup(f, " Search ", &input_clone, size);
+        }
+        AppState::Goto(input) => {
+            let input_clone = input.clone();
+            draw_diff_view(f, app, size);
+            draw_input_popup(f, " Go to JSONPath or /pointer ", &input_clone, size);
         }
     }
 }
 
-fn draw_saving_popup(f: &mut Frame, input: &str, area: Rect) {
+fn draw_input_popup(f: &mut Frame, title: &str, input: &str, area: Rect) {
+    draw_popup(f, title, input, " [Enter]: Confirm or [Esc]: Cancel ", area);
+}
+
+fn draw_popup(f: &mut Frame, title: &str, input: &str, hint: &str, area: Rect) {
     let popup_area = centered_rect(50, 5, area); // Increased height to 5
     
     // Clear the background of the popup area
@@ -507,7 +1942,7 @@ fn draw_saving_popup(f: &mut Frame, input: &str, area: Rect) {
     
     let block = Block::default()
         .borders(Borders::ALL)
-        .title(" Save As ")
+        .title(title)
         .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
         .border_style(Style::default().fg(Color::Yellow));
         
@@ -527,7 +1962,7 @@ fn draw_saving_popup(f: &mut Frame, input: &str, area: Rect) {
         .style(Style::default().fg(Color::White));
     f.render_widget(p, chunks[0]);
     
-    let hint = Paragraph::new(" [Enter]: Confirm or [Esc]: Cancel ")
+    let hint = Paragraph::new(hint)
         .alignment(Alignment::Center)
         .style(Style::default().fg(Color::DarkGray));
     f.render_widget(hint, chunks[2]);
@@ -545,8 +1980,29 @@ fn draw_diff_view(f: &mut Frame, app: &mut App, area: Rect) {
 
     // HEADER
     let header_style = Style::default().fg(Color::White).bg(HEADER_BG).add_modifier(Modifier::BOLD);
-    let header_text = format!(" {} ◄──► {} ", app.file1_name, app.file2_name);
-    f.render_widget(Paragraph::new(header_text).alignment(Alignment::Center).style(header_style), layout[0]);
+    let (line1, line2) = app.focus_lines(layout[1].height as usize);
+    let path1 = json_path_at(app.file1.as_deref(), line1);
+    let path2 = json_path_at(app.file2.as_deref(), line2);
+    if path1.is_none() && path2.is_none() {
+        let header_text = match &app.base_name {
+            Some(base) => format!(" {} ◄──► {} (base: {}) ", app.file1_name, app.file2_name, base),
+            None => format!(" {} ◄──► {} ", app.file1_name, app.file2_name),
+        };
+        f.render_widget(Paragraph::new(header_text).alignment(Alignment::Center).style(header_style), layout[0]);
+    } else {
+        // One half per pane: name, then where in the document that pane is
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(layout[0]);
+        let base = app.base_name.as_ref().map(|b| format!(" (base: {})", b)).unwrap_or_default();
+        for (area, name, path) in [(halves[0], format!(" {}{} ", app.file1_name, base), path1), (halves[1], format!(" {} ", app.file2_name), path2)] {
+            let room = (area.width as usize).saturating_sub(name.chars().count() + 1);
+            let path = truncate_front(&path.unwrap_or_default(), room);
+            let line = Line::from(vec![Span::raw(name), Span::styled(path, Style::default().fg(Color::Yellow))]);
+            f.render_widget(Paragraph::new(line).style(header_style), area);
+        }
+    }
 
     // FOOTER
     let footer_style = Style::default().fg(Color::White).bg(HEADER_BG).add_modifier(Modifier::BOLD);
@@ -560,13 +2016,18 @@ fn draw_diff_view(f: &mut Frame, app: &mut App, area: Rect) {
     let total_count = app.resolutions.len();
     
     // Condense info into one line
-    let help_text = format!(" [↑/↓/N/P]: Navigate | [1/2/3/←/→]: Pick | [Backspace]: Reset | [S]: Save | [Q]: Quit | Diff: {}/{} | Resolved: {}/{} ", 
+    let help_text = format!(" [↑/↓/N/P]: Navigate | [1/2/3/←/→]: Pick | [4]: Edit | [Backspace]: Reset | [Tab]: Structural | [S]: Save | [Q]: Quit | Diff: {}/{} | Resolved: {}/{} ", 
         sel_status, 
         total_count,
         resolved_count,
         total_count
     );
 
+    let help_text = match &app.status {
+        Some(status) => format!(" {} |{}", status, help_text),
+        None => help_text,
+    };
+
     f.render_widget(
         Paragraph::new(help_text)
             .alignment(Alignment::Center)
@@ -585,20 +2046,38 @@ fn draw_diff_view(f: &mut Frame, app: &mut App, area: Rect) {
     
     // Draw Backgrounds
     let left_block = Block::default()
-        .borders(Borders::RIGHT)
+        .borders(if app.unified { Borders::NONE } else { Borders::RIGHT })
         .border_style(Style::default().fg(BORDER_COLOR))
         .style(Style::default().bg(BG_CANVAS));
-    f.render_widget(left_block.clone(), chunks[0]);
-
     let right_block = Block::default()
         .style(Style::default().bg(BG_CANVAS));
-    f.render_widget(right_block.clone(), chunks[1]);
-    
-    let left_area = left_block.inner(chunks[0]);
-    let right_area = right_block.inner(chunks[1]);
+
+    // Unified view: one column over the whole width, with an extra gutter for old line numbers
+    let (left_area, right_area) = if app.unified {
+        f.render_widget(left_block.clone(), layout[1]);
+        let area = left_block.inner(layout[1]);
+        (area, area)
+    } else {
+        f.render_widget(left_block.clone(), chunks[0]);
+        f.render_widget(right_block.clone(), chunks[1]);
+        (left_block.inner(chunks[0]), right_block.inner(chunks[1]))
+    };
+    // Text columns after the gutter; the narrower pane sets the wrap width
+    let pane_width = if app.unified {
+        (left_area.width as usize).saturating_sub(8 + OLD_GUTTER_WIDTH).max(1)
+    } else {
+        (left_area.width.min(right_area.width) as usize).saturating_sub(8).max(1)
+    };
+    if pane_width != app.pane_width {
+        app.pane_width = pane_width;
+        if app.wrap {
+            app.rebuild_rows();
+        }
+    }
 
     // --- VIRTUAL RENDERING ---
     let start_row = app.scroll_offset;
+    let matcher = app.search.as_ref().map(|s| search::Matcher::new(&s.query));
     
     // Find the operation that contains start_row
     let start_op_idx = match app.op_row_counts.binary_search(&start_row) {
@@ -627,12 +2106,7 @@ fn draw_diff_view(f: &mut Frame, app: &mut App, area: Rect) {
         let op = &app.diff_ops[i];
         let op_start_row = app.op_row_counts[i];
         
-        let op_len = match op {
-            DiffOp::Equal { len, .. } => *len,
-            DiffOp::Delete { old_len, .. } => *old_len,
-            DiffOp::Insert { new_len, .. } => *new_len,
-            DiffOp::Replace { old_len, new_len, .. } => std::cmp::max(*old_len, *new_len),
-        };
+        let op_len = app.layout_rows(i);
         
         // Calculate overlap with view
         let offset_in_op = current_row_idx.saturating_sub(op_start_row);
@@ -642,89 +2116,62 @@ fn draw_diff_view(f: &mut Frame, app: &mut App, area: Rect) {
         let rows_to_render = rows_remaining.min(view_height - current_y);
         
         for r in 0..rows_to_render {
-             let local_idx = offset_in_op + r;
+             if current_y >= view_height { break; }
+             let local_idx = match app.row_content(i, offset_in_op + r) {
+                 RowContent::Line(local) => local,
+                 RowContent::Fold(hidden) => {
+                     // One row across both panes stands in for the folded lines
+                     let rect = Rect { x: layout[1].x, y: layout[1].y + current_y as u16, width: layout[1].width, height: 1 };
+                     let text = format!("⋯ {} unchanged lines", group_thousands(hidden));
+                     f.render_widget(Paragraph::new(text).alignment(Alignment::Center).style(Style::default().fg(Color::DarkGray).bg(BG_CANVAS)), rect);
+                     current_y += 1;
+                     current_row_idx += 1;
+                     continue;
+                 }
+             };
              let is_selected = app.selected_op_index == Some(i);
-             let resolution = app.resolutions.get(i).copied().unwrap_or(Resolution::Unresolved);
+             let resolution = app.resolutions.get(i).cloned().unwrap_or(Resolution::Unresolved);
              
              let default_gutter = Style::default().fg(LINE_NUM_FG).bg(BG_CANVAS);
              let selected_gutter = Style::default().fg(Color::Yellow).bg(Color::DarkGray).add_modifier(Modifier::BOLD);
              let gutter_style = if is_selected { selected_gutter } else { default_gutter };
+             let (mut left_emphasis, mut right_emphasis) = (Vec::new(), Vec::new());
+
+             let (left_line, right_line) = op_row_lines(op, local_idx, app.unified);
+
+             // A replaced line is compared with the line at the same position on the
+             // other side, whether that is shown next to it or further down
+             let mut is_visually_equal = false;
+             if let DiffOp::Replace { old_index, old_len, new_index, new_len } = *op {
+                 let k = left_line.map(|l| l - old_index).or(right_line.map(|r| r - new_index)).unwrap_or(0);
+                 if k < old_len && k < new_len {
+                     if let (Some(f1), Some(f2)) = (&app.file1, &app.file2) {
+                          if let (Some(l), Some(r)) = (f1.get_line(old_index + k), f2.get_line(new_index + k)) {
+                              if l == r {
+                                  is_visually_equal = true;
+                              } else {
+                                  if app.inline_spans.len() >= MAX_CACHED_INLINE_SPANS {
+                                      app.inline_spans.clear();
+                                  }
+                                  let mode = app.inline_mode;
+                                  (left_emphasis, right_emphasis) = app.inline_spans.entry((i, k)).or_insert_with(|| inline::changed_spans(l, r, mode)).clone();
+                              }
+                          }
+                     }
+                 }
+             }
 
-             let (mut left_cell, mut right_cell) = match op {
-                DiffOp::Equal { old_index, new_index, .. } => (
-                    DiffCell { 
-                        line_index: Some(old_index + local_idx),
-                        line_number: Some(old_index + local_idx + 1), 
-                        style: Style::default().fg(FG_DEFAULT).bg(BG_CANVAS),
-                        gutter_style
-                    },
-                    DiffCell { 
-                        line_index: Some(new_index + local_idx),
-                        line_number: Some(new_index + local_idx + 1), 
-                        style: Style::default().fg(FG_DEFAULT).bg(BG_CANVAS),
-                        gutter_style
-                    }
-                ),
-                DiffOp::Delete { old_index, .. } => (
-                     DiffCell { 
-                        line_index: Some(old_index + local_idx),
-                        line_number: Some(old_index + local_idx + 1), 
-                        style: Style::default().fg(FG_DEFAULT).bg(BG_DEL),
-                        gutter_style
-                    },
-                    DiffCell { line_index: None, line_number: None, style: Style::default().bg(BG_EMPTY), gutter_style }
-                ),
-                DiffOp::Insert { new_index, .. } => (
-                    DiffCell { line_index: None, line_number: None, style: Style::default().bg(BG_EMPTY), gutter_style },
-                    DiffCell { 
-                        line_index: Some(new_index + local_idx),
-                        line_number: Some(new_index + local_idx + 1), 
-                        style: Style::default().fg(FG_DEFAULT).bg(BG_ADD),
-                        gutter_style
-                    }
-                ),
-                DiffOp::Replace { old_index, old_len, new_index, new_len } => {
-                    let mut is_visually_equal = false;
-                    if local_idx < *old_len && local_idx < *new_len {
-                        if let (Some(f1), Some(f2)) = (&app.file1, &app.file2) {
-                             if let (Some(l), Some(r)) = (f1.get_line(old_index + local_idx), f2.get_line(new_index + local_idx)) {
-                                 if l == r { is_visually_equal = true; }
-                             }
-                        }
-                    }
-
-                     let left_cell = if local_idx < *old_len {
-                        DiffCell { 
-                            line_index: Some(old_index + local_idx),
-                            line_number: Some(old_index + local_idx + 1), 
-                            style: if is_visually_equal { 
-                                Style::default().fg(FG_DEFAULT).bg(BG_CANVAS) 
-                            } else { 
-                                Style::default().fg(FG_DEFAULT).bg(BG_DEL) 
-                            },
-                            gutter_style
-                        }
-                    } else {
-                        DiffCell { line_index: None, line_number: None, style: Style::default().bg(BG_EMPTY), gutter_style }
-                    };
-                    
-                    let right_cell = if local_idx < *new_len {
-                        DiffCell { 
-                            line_index: Some(new_index + local_idx),
-                            line_number: Some(new_index + local_idx + 1), 
-                            style: if is_visually_equal { 
-                                Style::default().fg(FG_DEFAULT).bg(BG_CANVAS) 
-                            } else { 
-                                Style::default().fg(FG_DEFAULT).bg(BG_ADD) 
-                            },
-                            gutter_style
-                        }
-                     } else {
-                        DiffCell { line_index: None, line_number: None, style: Style::default().bg(BG_EMPTY), gutter_style }
-                    };
-                    (left_cell, right_cell)
-                }
-            };
+             let unchanged = matches!(op, DiffOp::Equal { .. }) || is_visually_equal;
+             let cell = |line: Option<usize>, changed_bg: Color| match line {
+                 Some(idx) => DiffCell {
+                     line_index: Some(idx),
+                     line_number: Some(idx + 1),
+                     style: Style::default().fg(FG_DEFAULT).bg(if unchanged { BG_CANVAS } else { changed_bg }),
+                     gutter_style
+                 },
+                 None => DiffCell { line_index: None, line_number: None, style: Style::default().bg(BG_EMPTY), gutter_style },
+             };
+             let (mut left_cell, mut right_cell) = (cell(left_line, BG_DEL), cell(right_line, BG_ADD));
             
             // Apply Resolution Styles
             match resolution {
@@ -739,24 +2186,72 @@ fn draw_diff_view(f: &mut Frame, app: &mut App, area: Rect) {
                 Resolution::PickBoth => {
                      // Keep default styles (both visible)
                 },
+                Resolution::Edit(_) => {
+                     // Neither side is kept as is
+                     left_cell.style = left_cell.style.fg(Color::DarkGray).bg(BG_CANVAS);
+                     right_cell.style = right_cell.style.fg(Color::DarkGray).bg(BG_CANVAS);
+                },
                 Resolution::Unresolved => {
                     // Default
                 }
             }
             
-            // Render Left
-            let left_rect = Rect { x: left_area.x, y: left_area.y + current_y as u16, width: left_area.width, height: 1 };
-            if let Some(f1) = &app.file1 {
-                render_diff_line(f, &left_cell, left_rect, f1);
-            }
-            
-            // Render Right
-            let right_rect = Rect { x: right_area.x, y: right_area.y + current_y as u16, width: right_area.width, height: 1 };
-             if let Some(f2) = &app.file2 {
-                render_diff_line(f, &right_cell, right_rect, f2);
+            let left_marks = line_marks(&left_cell, &left_emphasis, matcher.as_ref(), app.file1.as_deref());
+            let right_marks = line_marks(&right_cell, &right_emphasis, matcher.as_ref(), app.file2.as_deref());
+
+            // A wrapped line takes several rows; the top one may be partly scrolled past
+            let height = app.line_rows(left_cell.line_index, right_cell.line_index);
+            let first_sub = if current_row_idx == start_row { app.wrap_skip.min(height - 1) } else { 0 };
+            let breaks = |view: Option<&LazyDiffView>, idx: Option<usize>| {
+                idx.and_then(|i| view?.get_line(i)).map(|l| wrap_breaks(l, app.pane_width)).unwrap_or_default()
+            };
+            let (left_breaks, right_breaks) = if app.wrap {
+                (breaks(app.file1.as_deref(), left_cell.line_index), breaks(app.file2.as_deref(), right_cell.line_index))
+            } else {
+                (vec![], vec![])
+            };
+            // Chars of a side shown on wrapped row `sub`; empty once its line ran out
+            let wrapped_cols = |breaks: &[usize], sub: usize| match breaks.get(sub) {
+                Some(&start) => start..breaks.get(sub + 1).copied().unwrap_or(usize::MAX),
+                None => 0..0,
+            };
+            for sub in first_sub..height {
+                if current_y >= view_height { break; }
+                let (left_cols, right_cols) = if app.wrap {
+                    (wrapped_cols(&left_breaks, sub), wrapped_cols(&right_breaks, sub))
+                } else {
+                    (app.h_scroll..usize::MAX, app.h_scroll..usize::MAX)
+                };
+                // Continuation rows carry no line number
+                let (left, right) = if sub == 0 {
+                    (left_cell, right_cell)
+                } else {
+                    (DiffCell { line_number: None, ..left_cell }, DiffCell { line_number: None, ..right_cell })
+                };
+
+                if app.unified {
+                    let rect = Rect { x: left_area.x, y: left_area.y + current_y as u16, width: left_area.width, height: 1 };
+                    // Unified rows show the old line when there is one
+                    let cols = if left.line_index.is_some() { left_cols } else { right_cols };
+                    render_unified_line(f, rect, (&left, app.file1.as_deref(), &left_marks), (&right, app.file2.as_deref(), &right_marks), cols);
+                    current_y += 1;
+                    continue;
+                }
+
+                // Render Left
+                let left_rect = Rect { x: left_area.x, y: left_area.y + current_y as u16, width: left_area.width, height: 1 };
+                if let Some(f1) = &app.file1 {
+                    render_diff_line(f, &left, left_rect, f1, &left_marks, left_cols);
+                }
+
+                // Render Right
+                let right_rect = Rect { x: right_area.x, y: right_area.y + current_y as u16, width: right_area.width, height: 1 };
+                if let Some(f2) = &app.file2 {
+                    render_diff_line(f, &right, right_rect, f2, &right_marks, right_cols);
+                }
+
+                current_y += 1;
             }
-            
-            current_y += 1;
             current_row_idx += 1;
         }
     }
@@ -774,7 +2269,185 @@ fn draw_diff_view(f: &mut Frame, app: &mut App, area: Rect) {
     );
 }
 
-fn render_diff_line(f: &mut Frame, cell: &DiffCell, area: Rect, source: &LazyDiffView) {
+/// Char index each wrapped row of `line` starts at, fitting `width` display
+/// columns per row. Always at least one row.
+fn wrap_breaks(line: &str, width: usize) -> Vec<usize> {
+    let mut breaks = vec![0];
+    let mut used = 0;
+    for (i, c) in line.chars().enumerate() {
+        let w = c.width().unwrap_or(0);
+        // A wide char that does not fit moves to the next row, unless that row is empty
+        if used + w > width && used > 0 {
+            breaks.push(i);
+            used = 0;
+        }
+        used += w;
+    }
+    breaks
+}
+
+fn json_path_at(view: Option<&LazyDiffView>, line: usize) -> Option<String> {
+    let view = view?;
+    let line = line.min(view.len().saturating_sub(1));
+    Some(view.paths.as_ref()?.path_of(view, line)?.to_string())
+}
+
+// 12345 -> "12,345"
+fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// Keeps the end of a long path, which is the informative part
+fn truncate_front(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    if len <= width {
+        return text.to_string();
+    }
+    let tail: String = text.chars().skip(len - width.saturating_sub(1)).collect();
+    if width == 0 { String::new() } else { format!("…{}", tail) }
+}
+
+fn draw_structural_view(f: &mut Frame, app: &mut App, area: Rect) {
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1), // Header
+            Constraint::Min(0),    // Content
+            Constraint::Length(1), // Footer
+        ])
+        .split(area);
+
+    let bar_style = Style::default().fg(Color::White).bg(HEADER_BG).add_modifier(Modifier::BOLD);
+    let header_text = format!(" {} ◄──► {} (Structural) ", app.file1_name, app.file2_name);
+    f.render_widget(Paragraph::new(header_text).alignment(Alignment::Center).style(bar_style), layout[0]);
+
+    let count = match app.structural_dropped {
+        0 => group_thousands(app.structural_len()),
+        dropped => format!("first {} of {} (use --format structural for all)", group_thousands(app.structural_len()), group_thousands(app.structural_len() + dropped)),
+    };
+    let help_text = format!(" [Tab]: Line View | [↑/↓/PgUp/PgDn]: Scroll | [Q]: Quit | Changes: {} ", count);
+    f.render_widget(Paragraph::new(help_text).alignment(Alignment::Center).style(bar_style), layout[2]);
+
+    let content = layout[1];
+    let changes = match &app.structural {
+        Some(c) => c,
+        None => {
+            let p = Paragraph::new("Structural diff unavailable: both inputs must be valid JSON")
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(Color::DarkGray));
+            f.render_widget(p, centered_rect(80, 10, content));
+            return;
+        }
+    };
+    if changes.is_empty() {
+        let p = Paragraph::new("No structural differences")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(p, centered_rect(80, 10, content));
+        return;
+    }
+
+    // Only the visible slice is formatted
+    let max_width = content.width as usize;
+    let lines: Vec<Line> = changes
+        .iter()
+        .skip(app.structural_scroll)
+        .take(content.height as usize)
+        .map(|change| {
+            let marker_style = match change.kind {
+                ChangeKind::Added(_) => Style::default().fg(FG_ADD).bg(BG_ADD),
+                ChangeKind::Removed(_) => Style::default().fg(FG_DEL).bg(BG_DEL),
+                ChangeKind::Changed { .. } => Style::default().fg(Color::Black).bg(Color::Yellow),
+                ChangeKind::TypeChanged { .. } => Style::default().fg(Color::White).bg(Color::Magenta),
+                ChangeKind::Moved { .. } => Style::default().fg(Color::Black).bg(Color::Cyan),
+            };
+            let path = change.path.to_string();
+            let detail: String = change.kind.describe().chars().take(max_width.saturating_sub(path.chars().count() + 5)).collect();
+            Line::from(vec![
+                Span::styled(format!(" {} ", change.kind.marker()), marker_style),
+                Span::styled(format!(" {} ", path), Style::default().fg(FG_DEFAULT).add_modifier(Modifier::BOLD)),
+                Span::styled(detail, Style::default().fg(LINE_NUM_FG)),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), content);
+
+    let mut scroll_state = ScrollbarState::new(changes.len()).position(app.structural_scroll);
+    f.render_stateful_widget(
+        Scrollbar::default()
+            .orientation(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .track_symbol(None)
+            .thumb_symbol("▐")
+            .style(Style::default().fg(Color::DarkGray)),
+        content,
+        &mut scroll_state,
+    );
+}
+
+/// Styled byte ranges of a line: changed words in a stronger shade of the
+/// cell background, then search matches (drawn last, so they win).
+fn line_marks(cell: &DiffCell, emphasis: &[Range<usize>], matcher: Option<&search::Matcher>, source: Option<&LazyDiffView>) -> Vec<(Range<usize>, Style)> {
+    // A dimmed (resolved-away) cell keeps its plain style
+    let emphasis_style = if cell.style.bg == Some(BG_DEL) {
+        cell.style.bg(BG_DEL_EMPH).add_modifier(Modifier::BOLD)
+    } else if cell.style.bg == Some(BG_ADD) {
+        cell.style.bg(BG_ADD_EMPH).add_modifier(Modifier::BOLD)
+    } else {
+        cell.style
+    };
+    let mut marks: Vec<(Range<usize>, Style)> = emphasis.iter().map(|r| (r.clone(), emphasis_style)).collect();
+
+    if let (Some(matcher), Some(line)) = (matcher, cell.line_index.and_then(|i| source?.get_line(i))) {
+        let match_style = Style::default().fg(Color::Black).bg(BG_MATCH);
+        marks.extend(matcher.find_ranges(line).into_iter().map(|r| (r, match_style)));
+    }
+    marks
+}
+
+// One side of a unified row: cell, file, marks
+type UnifiedSide<'a> = (&'a DiffCell, Option<&'a LazyDiffView>, &'a [(Range<usize>, Style)]);
+
+/// A unified row: the old line number, then the line as `render_diff_line` draws
+/// it with the new line number, marked `-`, `+`, or neither for unchanged lines.
+fn render_unified_line(f: &mut Frame, area: Rect, left: UnifiedSide, right: UnifiedSide, cols: Range<usize>) {
+    let (old_cell, old_source, old_marks) = left;
+    let (new_cell, new_source, new_marks) = right;
+    let first_row = old_cell.line_number.or(new_cell.line_number).is_some();
+    let (sign, cell, source, marks) = match (old_cell.line_index, new_cell.line_index) {
+        (Some(_), Some(_)) => (' ', DiffCell { line_number: new_cell.line_number, ..*old_cell }, old_source, old_marks),
+        (Some(_), None) => ('-', DiffCell { line_number: None, ..*old_cell }, old_source, old_marks),
+        _ => ('+', *new_cell, new_source, new_marks),
+    };
+    let Some(source) = source else { return };
+
+    let old_num = match old_cell.line_number {
+        Some(n) if cell.line_index == old_cell.line_index => format!("{:>4} ", n),
+        _ => " ".repeat(OLD_GUTTER_WIDTH),
+    };
+    f.buffer_mut().set_string(area.x, area.y, old_num, cell.gutter_style);
+
+    let gutter = OLD_GUTTER_WIDTH as u16;
+    let rest = Rect { x: area.x + gutter, width: area.width.saturating_sub(gutter), ..area };
+    render_diff_line(f, &cell, rest, source, marks, cols);
+    if first_row && sign != ' ' && rest.width > 6 {
+        // The blank column between the separator and the text
+        f.buffer_mut().set_string(rest.x + 6, rest.y, sign.to_string(), cell.style);
+    }
+}
+
+/// `marks` restyle byte ranges of the line (see `line_marks`).
+/// Only the chars in `cols` are drawn (horizontal scroll or one wrapped row).
+fn render_diff_line(f: &mut Frame, cell: &DiffCell, area: Rect, source: &LazyDiffView, marks: &[(Range<usize>, Style)], cols: Range<usize>) {
     let buf = f.buffer_mut();
     
     // 1. Fill background for the entire line
@@ -817,17 +2490,35 @@ fn render_diff_line(f: &mut Frame, cell: &DiffCell, area: Rect, source: &LazyDif
     // Draw Text
     if let Some(idx) = cell.line_index {
         if let Some(line) = source.get_line(idx) {
-             let max_width = (area.width as usize).saturating_sub(7); // 5 num + 1 space + 1 separator + 1 space
+             let max_width = (area.width as usize).saturating_sub(8).min(cols.len()); // 5 num + 1 space + 1 separator + 1 space
              
              // Optimization: Use chars().take() to prevent panic on unicode boundaries and truncation
-             let display_content: String = line.chars().take(max_width).collect();
-             
-             buf.set_string(
-                 content_x, 
-                 area.y, 
-                 format!(" {}", display_content), // Add leading space
-                 cell.style
-             );
+             if marks.is_empty() {
+                 let display_content: String = line.chars().skip(cols.start).take(max_width).collect();
+
+                 buf.set_string(
+                     content_x,
+                     area.y,
+                     format!(" {}", display_content), // Add leading space
+                     cell.style
+                 );
+                 return;
+             }
+
+             // Split the visible text into runs of equally styled chars
+             let mut spans = vec![Span::styled(" ", cell.style)];
+             let mut run = String::new();
+             let mut run_style = cell.style;
+             for (i, c) in line.char_indices().skip(cols.start).take(max_width) {
+                 let style = marks.iter().rev().find(|(r, _)| r.contains(&i)).map(|(_, s)| *s).unwrap_or(cell.style);
+                 if style != run_style && !run.is_empty() {
+                     spans.push(Span::styled(std::mem::take(&mut run), run_style));
+                 }
+                 run_style = style;
+                 run.push(c);
+             }
+             spans.push(Span::styled(run, run_style));
+             buf.set_line(content_x, area.y, &Line::from(spans), area.right().saturating_sub(content_x));
         }
     }
 }
@@ -841,7 +2532,7 @@ fn draw_loading(f: &mut Frame, app: &mut App, area: Rect) {
             Span::styled(frame, Style::default().fg(FG_ADD).add_modifier(Modifier::BOLD)),
             Span::styled(format!(" Analyzing files... (Large File Mode: {})", if app.file1.as_ref().map(|f| f.len() > 100000).unwrap_or(false) { "ON" } else { "AUTO" }), Style::default().fg(FG_DEFAULT)),
         ]),
-        Line::from(Span::styled(format!("{}", app.loading_log), Style::default().fg(Color::DarkGray))),
+        Line::from(Span::styled(app.loading_log.to_string(), Style::default().fg(Color::DarkGray))),
     ];
     
     let p = Paragraph::new(text).alignment(Alignment::Center);
@@ -875,34 +2566,137 @@ fn centered_rect(w: u16, h: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-fn process_side_by_side(p1: PathBuf, p2: PathBuf, tx: Sender<AppEvent>) {
-    let internal_process = || -> Result<(LazyDiffView, LazyDiffView, Vec<DiffOp>)> {
+fn process_side_by_side(p1: PathBuf, p2: PathBuf, options: DiffOptions, tx: Sender<AppEvent>) {
+    let internal_process = || -> Result<DiffResult> {
         let p1_display = p1.to_string_lossy();
         let p2_display = p2.to_string_lossy();
 
         let _ = tx.send(AppEvent::Log(format!("Reading {}", p1_display)));
-        let f1 = LazyDiffView::new(&p1).context("Failed to read file 1")?;
+        let mut f1 = LazyDiffView::new(&p1).context("Failed to read file 1")?;
         
         let _ = tx.send(AppEvent::Log(format!("Reading {}", p2_display)));
-        let f2 = LazyDiffView::new(&p2).context("Failed to read file 2")?;
+        let mut f2 = LazyDiffView::new(&p2).context("Failed to read file 2")?;
+
+        let (changes, changes_dropped) = structural_changes(&mut f1, &mut f2, &options, &tx);
 
         let _ = tx.send(AppEvent::Log("Calculating Diff (imara-diff)...".to_string()));
-        let algorithm = Algorithm::Histogram;
-        
-        // Intern inputs
-        let input = InternedInput::new(
-            byte_lines(&f1.content), 
-            byte_lines(&f2.content)
-        );
-        
-        let sink = DiffSink::new(f1.len(), f2.len());
-        let ops = diff(algorithm, &input, sink);
+        let ops = line_diff(&f1, &f2);
         
-        Ok((f1, f2, ops))
+        Ok(DiffResult { file1: f1, file2: f2, ops, changes, changes_dropped, resolutions: None })
+    };
+
+    let res = internal_process();
+    let _ = tx.send(AppEvent::Done(res.map(Box::new)));
+}
+
+/// Three-way variant: diffs OURS against THEIRS, pre-resolving every hunk
+/// that only one side changed relative to BASE.
+fn process_three_way(base: PathBuf, ours: PathBuf, theirs: PathBuf, options: DiffOptions, tx: Sender<AppEvent>) {
+    let internal_process = || -> Result<DiffResult> {
+        let _ = tx.send(AppEvent::Log(format!("Reading {}", base.to_string_lossy())));
+        let fb = LazyDiffView::new(&base).context("Failed to read base file")?;
+
+        let _ = tx.send(AppEvent::Log(format!("Reading {}", ours.to_string_lossy())));
+        let mut f1 = LazyDiffView::new(&ours).context("Failed to read file 1")?;
+
+        let _ = tx.send(AppEvent::Log(format!("Reading {}", theirs.to_string_lossy())));
+        let mut f2 = LazyDiffView::new(&theirs).context("Failed to read file 2")?;
+
+        let (changes, changes_dropped) = structural_changes(&mut f1, &mut f2, &options, &tx);
+
+        let _ = tx.send(AppEvent::Log("Calculating Three-Way Merge (imara-diff)...".to_string()));
+        let base_to_ours = line_diff(&fb, &f1);
+        let base_to_theirs = line_diff(&fb, &f2);
+        let (ops, resolutions) = merge::merge_ops(&base_to_ours, &base_to_theirs, &f1, &f2, fb.len());
+
+        Ok(DiffResult { file1: f1, file2: f2, ops, changes, changes_dropped, resolutions: Some(resolutions) })
     };
 
     let res = internal_process();
-    let _ = tx.send(AppEvent::Done(res));
+    let _ = tx.send(AppEvent::Done(res.map(Box::new)));
+}
+
+// Structural diff needs both parsed trees; drop them afterwards to free memory.
+// Files too large to parse are diffed straight from the mmap instead.
+// Streamed changes past the first MAX_STREAMED_CHANGES are only counted.
+fn structural_changes(f1: &mut LazyDiffView, f2: &mut LazyDiffView, options: &DiffOptions, tx: &Sender<AppEvent>) -> (Option<Vec<StructuralChange>>, usize) {
+    match (f1.value.take(), f2.value.take()) {
+        (Some(v1), Some(v2)) => {
+            let _ = tx.send(AppEvent::Log("Calculating Structural Diff...".to_string()));
+            (Some(structural::diff_values(&v1, &v2, options)), 0)
+        }
+        _ if streams_json(f1, f2) => {
+            let _ = tx.send(AppEvent::Log("Calculating Structural Diff (streaming)...".to_string()));
+            match collect_streamed(f1, f2) {
+                Ok((changes, dropped)) => (Some(changes), dropped),
+                Err(e) => {
+                    let _ = tx.send(AppEvent::Log(format!("Structural diff unavailable: {}", e)));
+                    (None, 0)
+                }
+            }
+        }
+        _ => (None, 0),
+    }
+}
+
+// The first MAX_STREAMED_CHANGES streamed changes, and how many more there were
+fn collect_streamed(f1: &LazyDiffView, f2: &LazyDiffView) -> Result<(Vec<StructuralChange>, usize)> {
+    let (mut changes, mut dropped) = (Vec::new(), 0);
+    stream::diff_streams(&f1.content, &f2.content, &mut |change| {
+        if changes.len() < MAX_STREAMED_CHANGES {
+            changes.push(change);
+        } else {
+            dropped += 1;
+        }
+        Ok(())
+    })?;
+    Ok((changes, dropped))
+}
+
+// Too large to parse, but both JSON: the structural diff streams over the bytes
+fn streams_json(f1: &LazyDiffView, f2: &LazyDiffView) -> bool {
+    (f1.is_mapped() || f2.is_mapped()) && stream::looks_like_json(&f1.content) && stream::looks_like_json(&f2.content)
+}
+
+/// Rows an op takes: side by side, or stacked (all `-` lines, then all `+`) when unified.
+fn op_rows(op: &DiffOp, unified: bool) -> usize {
+    match *op {
+        DiffOp::Equal { len, .. } => len,
+        DiffOp::Delete { old_len, .. } => old_len,
+        DiffOp::Insert { new_len, .. } => new_len,
+        DiffOp::Replace { old_len, new_len, .. } if unified => old_len + new_len,
+        DiffOp::Replace { old_len, new_len, .. } => old_len.max(new_len),
+    }
+}
+
+/// Line indices shown on each side of row `local` of an op.
+fn op_row_lines(op: &DiffOp, local: usize, unified: bool) -> (Option<usize>, Option<usize>) {
+    match *op {
+        DiffOp::Equal { old_index, new_index, .. } => (Some(old_index + local), Some(new_index + local)),
+        DiffOp::Delete { old_index, .. } => (Some(old_index + local), None),
+        DiffOp::Insert { new_index, .. } => (None, Some(new_index + local)),
+        DiffOp::Replace { old_index, old_len, new_index, .. } if unified => match local.checked_sub(old_len) {
+            None => (Some(old_index + local), None),
+            Some(k) => (None, Some(new_index + k)),
+        },
+        DiffOp::Replace { old_index, old_len, new_index, new_len } => (
+            (local < old_len).then_some(old_index + local),
+            (local < new_len).then_some(new_index + local),
+        ),
+    }
+}
+
+fn line_diff(f1: &LazyDiffView, f2: &LazyDiffView) -> Vec<DiffOp> {
+    let algorithm = Algorithm::Histogram;
+
+    // Intern inputs
+    let input = InternedInput::new(
+        byte_lines(&f1.content), 
+        byte_lines(&f2.content)
+    );
+
+    let sink = DiffSink::new(f1.len(), f2.len());
+    diff(algorithm, &input, sink)
 }
 
 struct DiffSink {
@@ -990,123 +2784,173 @@ impl Sink for DiffSink {
     }
 }
 
-fn save_merged_output(app: &App, path: &str) -> anyhow::Result<()> {
+/// Opens `$VISUAL` / `$EDITOR` (else `vi`) on a temp file holding `seed`.
+/// Returns the saved text, or None if it was left unchanged.
+fn edit_in_editor(seed: &str) -> Result<Option<String>> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "json-diff-edit-{}-{}.json",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&path, seed).context("Failed to create the temp file")?;
+
+    let result = (|| -> Result<Option<String>> {
+        let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+        // Allow editors that need flags, e.g. `code --wait`
+        let mut words = editor.split_whitespace();
+        let program = words.next().context("$EDITOR is empty")?;
+        let status = std::process::Command::new(program)
+            .args(words)
+            .arg(&path)
+            .status()
+            .with_context(|| format!("Failed to run {}", editor))?;
+        if !status.success() {
+            anyhow::bail!("{} exited with {}", editor, status);
+        }
+        let text = fs::read_to_string(&path).context("Failed to read the edited file")?;
+        Ok((text != seed).then_some(text))
+    })();
+    let _ = fs::remove_file(&path);
+    result
+}
+
+fn create_output(path: &str) -> Result<BufWriter<File>> {
     let file = File::create(path).context("Failed to create output file")?;
-    let mut writer = BufWriter::new(file);
-    
+    Ok(BufWriter::new(file))
+}
+
+/// x: writes the line diff with the folding context, as an HTML report if
+/// the name ends in `.html`, otherwise as a unified diff.
+fn export_patch(app: &App, path: &str) -> Result<()> {
+    let mut writer = create_output(path)?;
     let f1 = app.file1.as_ref().context("File 1 not loaded")?;
     let f2 = app.file2.as_ref().context("File 2 not loaded")?;
-    
+    if path.ends_with(".html") || path.ends_with(".htm") {
+        let names = (app.file1_name.as_str(), app.file2_name.as_str());
+        html::write_html(&mut writer, f1, f2, &app.diff_ops, names, app.context, &html_palette())?;
+    } else {
+        let setup = app.setup.as_ref().context("Nothing loaded to export")?;
+        let raw = raw_patch_views(f1, f2, (&setup.left, &setup.right))?;
+        let (f1, f2, ops) = match &raw {
+            Some((f1, f2, ops)) => (f1, f2, ops),
+            None => (&**f1, &**f2, &app.diff_ops),
+        };
+        let (a, b) = &app.patch_labels;
+        export::write_unified(&mut writer, f1, f2, ops, (a, b), app.context)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// A patch must apply to the files as they are, not to the pretty-printed
+/// form shown: when either input was reformatted, re-reads both and diffs
+/// their own lines instead.
+fn raw_patch_views(f1: &LazyDiffView, f2: &LazyDiffView, paths: (&PathBuf, &PathBuf)) -> Result<Option<(LazyDiffView, LazyDiffView, Vec<DiffOp>)>> {
+    if !f1.reformatted && !f2.reformatted {
+        return Ok(None);
+    }
+    let raw1 = LazyDiffView::raw(paths.0).context("Failed to read file 1")?;
+    let raw2 = LazyDiffView::raw(paths.1).context("Failed to read file 2")?;
+    let ops = line_diff(&raw1, &raw2);
+    Ok(Some((raw1, raw2, ops)))
+}
+
+// Patch labels without git's a/ and b/ prefixes
+fn file_label(label: &str) -> &str {
+    label.strip_prefix("a/").or_else(|| label.strip_prefix("b/")).unwrap_or(label)
+}
+
+// The HTML report uses the TUI colors, as xterm draws them
+fn html_palette() -> html::Palette {
+    fn css(color: Color) -> &'static str {
+        match color {
+            Color::Red => "#cd0000",
+            Color::LightRed => "#ff6e6e",
+            Color::Green => "#00cd00",
+            Color::LightGreen => "#7dff7d",
+            Color::White => "#ffffff",
+            Color::Black => "#000000",
+            _ => "inherit",
+        }
+    }
+    html::Palette {
+        del: css(BG_DEL),
+        del_emph: css(BG_DEL_EMPH),
+        del_fg: css(FG_DEL),
+        add: css(BG_ADD),
+        add_emph: css(BG_ADD_EMPH),
+        add_fg: css(FG_ADD),
+    }
+}
+
+/// Writes the inputs, options and current resolutions for `--resume`.
+fn save_session(app: &App, path: &str) -> Result<()> {
+    let setup = app.setup.as_ref().context("Nothing loaded to save")?;
+    session::Session::capture(setup, &app.resolutions)?.save(std::path::Path::new(path))
+}
+
+// Where a stretch of the merged output comes from
+enum Piece<'a> {
+    Left(Range<usize>),
+    Right(Range<usize>),
+    Text(&'a str),
+}
+
+/// What the merged output contains for one op.
+fn merged_pieces<'a>(op: &DiffOp, resolution: &'a Resolution) -> Vec<Piece<'a>> {
+    let (old, new) = (op.old_range(), op.new_range());
+    match (op, resolution) {
+        (DiffOp::Equal { .. }, _) => vec![Piece::Left(old)],
+        (_, Resolution::Edit(text)) => vec![Piece::Text(text)],
+        // Accepts an insert or a delete
+        (_, Resolution::PickRight) => vec![Piece::Right(new)],
+        (_, Resolution::PickBoth) => vec![Piece::Left(old), Piece::Right(new)],
+        // Pick Left or unresolved: keep File 1 (an insert adds nothing)
+        _ => vec![Piece::Left(old)],
+    }
+}
+
+/// Writes the merged output. When both inputs are JSON it is checked while
+/// it streams out, and the first syntax error is returned, tagged with its op.
+fn write_merged<W: Write>(app: &App, out: &mut W) -> Result<Option<validate::SyntaxError>> {
+    let f1 = app.file1.as_ref().context("File 1 not loaded")?;
+    let f2 = app.file2.as_ref().context("File 2 not loaded")?;
+    let mut validator = app.merges_json().then(validate::Validator::default);
+    let mut error = None;
+
     for (i, op) in app.diff_ops.iter().enumerate() {
-        let resolution = app.resolutions.get(i).copied().unwrap_or(Resolution::Unresolved);
-        
-        match op {
-            DiffOp::Equal { old_index, len, .. } => {
-                let start_idx = *old_index;
-                let end_idx = start_idx + len;
-                
-                // Get byte range from line offsets
-                let start_byte = f1.line_offsets[start_idx];
-                let end_byte = if end_idx < f1.line_offsets.len() {
-                    f1.line_offsets[end_idx]
-                } else {
-                    f1.content.len()
-                };
-                
-                writer.write_all(&f1.content[start_byte..end_byte])?;
-            }
-            DiffOp::Insert { new_index, new_len, .. } => {
-                match resolution {
-                    Resolution::PickRight | Resolution::PickBoth => {
-                        let start_idx = *new_index;
-                        let end_idx = start_idx + new_len;
-                        
-                        let start_byte = f2.line_offsets[start_idx];
-                        let end_byte = if end_idx < f2.line_offsets.len() {
-                            f2.line_offsets[end_idx]
-                        } else {
-                            f2.content.len()
-                        };
-                        writer.write_all(&f2.content[start_byte..end_byte])?;
-                    }
-                    _ => {}
-                }
-            }
-            DiffOp::Delete { old_index, old_len, .. } => {
-                match resolution {
-                     Resolution::PickRight => {} // Skip (Accept Delete)
-                     _ => {
-                        // PickLeft (Reject Delete), Unresolved (Default Keep), PickBoth (Keep)
-                        let start_idx = *old_index;
-                        let end_idx = start_idx + old_len;
-                        
-                        let start_byte = f1.line_offsets[start_idx];
-                        let end_byte = if end_idx < f1.line_offsets.len() {
-                            f1.line_offsets[end_idx]
-                        } else {
-                            f1.content.len()
-                        };
-                        writer.write_all(&f1.content[start_byte..end_byte])?;
-                     }
-                }
-            }
-            DiffOp::Replace { old_index, old_len, new_index, new_len } => {
-                match resolution {
-                    Resolution::PickRight => {
-                         let start_idx = *new_index;
-                        let end_idx = start_idx + new_len;
-                        
-                        let start_byte = f2.line_offsets[start_idx];
-                        let end_byte = if end_idx < f2.line_offsets.len() {
-                            f2.line_offsets[end_idx]
-                        } else {
-                            f2.content.len()
-                        };
-                        writer.write_all(&f2.content[start_byte..end_byte])?;
-                    }
-                    Resolution::PickBoth => {
-                        // Write File 1 chunk
-                        let start_idx_1 = *old_index;
-                        let end_idx_1 = start_idx_1 + old_len;
-                        let start_byte_1 = f1.line_offsets[start_idx_1];
-                        let end_byte_1 = if end_idx_1 < f1.line_offsets.len() {
-                            f1.line_offsets[end_idx_1]
-                        } else {
-                            f1.content.len()
-                        };
-                        writer.write_all(&f1.content[start_byte_1..end_byte_1])?;
-                        
-                        // Write File 2 chunk
-                        let start_idx_2 = *new_index;
-                        let end_idx_2 = start_idx_2 + new_len;
-                        let start_byte_2 = f2.line_offsets[start_idx_2];
-                        let end_byte_2 = if end_idx_2 < f2.line_offsets.len() {
-                            f2.line_offsets[end_idx_2]
-                        } else {
-                            f2.content.len()
-                        };
-                        writer.write_all(&f2.content[start_byte_2..end_byte_2])?;
-                    }
-                    _ => {
-                        // PickLeft or Unresolved -> Keep File 1
-                        let start_idx = *old_index;
-                        let end_idx = start_idx + old_len;
-                        
-                        let start_byte = f1.line_offsets[start_idx];
-                        let end_byte = if end_idx < f1.line_offsets.len() {
-                            f1.line_offsets[end_idx]
-                        } else {
-                            f1.content.len()
-                        };
-                        writer.write_all(&f1.content[start_byte..end_byte])?;
-                    }
-                }
+        let resolution = app.resolutions.get(i).unwrap_or(&Resolution::Unresolved);
+        if let Some(v) = &mut validator {
+            v.tag(i);
+        }
+        for piece in merged_pieces(op, resolution) {
+            let bytes = match piece {
+                Piece::Left(lines) => f1.line_bytes(lines),
+                Piece::Right(lines) => f2.line_bytes(lines),
+                Piece::Text(text) => text.as_bytes(),
+            };
+            out.write_all(bytes)?;
+            if let Some(Err(e)) = validator.as_mut().map(|v| v.feed(bytes)) {
+                error = Some(e);
+                validator = None;
             }
         }
     }
-    
+
+    if let Some(v) = validator {
+        error = v.finish().err();
+    }
+    Ok(error)
+}
+
+/// Saves the merged output. A syntax error doesn't stop the save; it is
+/// returned so the caller can report it.
+fn save_merged_output(app: &App, path: &str) -> Result<Option<validate::SyntaxError>> {
+    let mut writer = create_output(path)?;
+    let error = write_merged(app, &mut writer)?;
     writer.flush()?;
-    Ok(())
+    Ok(error)
 }
 
 #[cfg(test)]
@@ -1116,6 +2960,33 @@ mod tests {
     use std::fs::File;
     use std::io::Write;
 
+    // A view of `text` as it is, without reformatting
+    fn view(text: &str) -> Arc<LazyDiffView> {
+        Arc::new(LazyDiffView::from_source(ContentSource::Memory(text.as_bytes().to_vec())).unwrap())
+    }
+
+    // `value` pretty-printed, with its JSON path index
+    fn pretty(value: Value) -> Arc<LazyDiffView> {
+        let mut view = LazyDiffView::from_source(ContentSource::Memory(serde_json::to_string_pretty(&value).unwrap().into_bytes())).unwrap();
+        view.paths = PathIndex::build(&view);
+        Arc::new(view)
+    }
+
+    /// A finished diff of `ops` between `left` and `right`, all unresolved,
+    /// with its rows laid out.
+    fn test_app(ops: Vec<DiffOp>, left: impl Into<Option<Arc<LazyDiffView>>>, right: impl Into<Option<Arc<LazyDiffView>>>) -> App {
+        let mut app = App {
+            state: AppState::Done,
+            resolutions: vec![Resolution::Unresolved; ops.len()],
+            diff_ops: ops,
+            file1: left.into(),
+            file2: right.into(),
+            ..App::new(mpsc::channel().1, "f1".to_string(), "f2".to_string())
+        };
+        app.rebuild_rows();
+        app
+    }
+
     #[test]
     fn test_process_side_by_side_performance() {
         // Create large dummy files
@@ -1143,7 +3014,7 @@ mod tests {
         let p2_clone = p2.clone();
         
         let _ = thread::spawn(move || {
-            process_side_by_side(p1_clone, p2_clone, tx);
+            process_side_by_side(p1_clone, p2_clone, DiffOptions::default(), tx);
         });
 
         // Wait for result
@@ -1204,17 +3075,10 @@ mod tests {
             state: AppState::Done,
             diff_ops: diff_ops.clone(),
             op_row_counts: vec![], // Not needed for save
-            file1: Some(f1),
-            file2: Some(f2),
-            scroll_offset: 0,
-            scroll_state: ScrollbarState::default(),
-            spinner_index: 0,
-            receiver: std::sync::mpsc::channel().1,
-            file1_name: "f1".to_string(),
-            file2_name: "f2".to_string(),
-            loading_log: String::new(),
+            file1: Some(Arc::new(f1)),
+            file2: Some(Arc::new(f2)),
             resolutions: vec![Resolution::Unresolved; 4],
-            selected_op_index: None,
+            ..App::new(std::sync::mpsc::channel().1, "f1".to_string(), "f2".to_string())
         };
 
         // Case 1: All Unresolved -> Should match File 1 (Project "Our" changes)
@@ -1242,6 +3106,15 @@ mod tests {
         let saved = std::fs::read_to_string(&out)?;
         assert_eq!(saved, "A\nB\nMOD\nC\nD\n");
 
+        // Case 4: Edit replaces the hunk; the editor's newline is matched to the hunk's
+        assert!(app.edit_seed(1).starts_with("<<<<<<< f1\nB\n=======\nMOD\n>>>>>>> f2\n"));
+        app.apply_edit(1, "X\nY".to_string());
+        assert_eq!(app.resolutions[1], Resolution::Edit("X\nY\n".to_string()));
+        assert_eq!(app.edit_seed(1), "X\nY\n");
+        save_merged_output(&app, out.to_str().unwrap())?;
+        let saved = std::fs::read_to_string(&out)?;
+        assert_eq!(saved, "A\nX\nY\nC\nD\n");
+
         // Cleanup
         let _ = std::fs::remove_file(p1);
         let _ = std::fs::remove_file(p2);
@@ -1267,15 +3140,8 @@ mod tests {
             op_row_counts,
             file1: None, // Not needed for logic test
             file2: None,
-            scroll_offset: 0,
-            scroll_state: ScrollbarState::default(),
-            spinner_index: 0,
-            receiver: std::sync::mpsc::channel().1,
-            file1_name: "f1".to_string(),
-            file2_name: "f2".to_string(),
-            loading_log: String::new(),
             resolutions: vec![Resolution::Unresolved; 3],
-            selected_op_index: None,
+            ..App::new(std::sync::mpsc::channel().1, "f1".to_string(), "f2".to_string())
         };
 
         // 2. Simulate 'n' (Next Hunk) from None
@@ -1318,6 +3184,317 @@ mod tests {
         assert_eq!(app.selected_op_index, Some(1)); // Remained 1
     }
 
+    #[test]
+    fn test_wrap_scrolls_through_continuation_rows() {
+        let long = "b".repeat(25);
+        let text = format!("a\n{}\nc", long);
+        let mut app = test_app(vec![DiffOp::Equal { old_index: 0, new_index: 0, len: 3 }], view(&text), view(&text));
+        app.pane_width = 10;
+        app.toggle_wrap();
+
+        // The 25-char line wraps into 3 rows at width 10
+        let mut positions = vec![];
+        for _ in 0..5 {
+            app.scroll_down(1);
+            positions.push((app.scroll_offset, app.wrap_skip));
+        }
+        assert_eq!(positions, vec![(1, 0), (1, 1), (1, 2), (2, 0), (2, 0)]);
+
+        app.scroll_up(1);
+        assert_eq!((app.scroll_offset, app.wrap_skip), (1, 2));
+
+        app.toggle_wrap();
+        app.scroll_to(0);
+        app.scroll_down(2);
+        assert_eq!((app.scroll_offset, app.wrap_skip), (2, 0));
+    }
+
+    #[test]
+    fn test_wrap_counts_visual_rows() {
+        let text = format!("a\n{}\nc\n", "b".repeat(25));
+        let mut app = test_app(vec![DiffOp::Equal { old_index: 0, new_index: 0, len: 3 }], view(&text), view(&text));
+        app.pane_width = 10;
+        app.toggle_wrap();
+        assert_eq!(app.wrap_starts, vec![0, 1, 4, 5]);
+
+        // End lands on the last visual row, and the scrollbar counts visual rows
+        app.scroll_to(1);
+        app.scroll_down(1);
+        assert_eq!(app.visual_position(), (2, 5));
+        app.scroll_to_end();
+        assert_eq!((app.scroll_offset, app.visual_position()), (2, (4, 5)));
+
+        // A wider pane wraps less
+        app.pane_width = 20;
+        app.rebuild_rows();
+        assert_eq!(app.wrap_starts, vec![0, 1, 3, 4]);
+
+        app.toggle_wrap();
+        assert_eq!(app.visual_position(), (2, 3));
+    }
+
+    #[test]
+    fn test_wrap_breaks_by_display_width() {
+        assert_eq!(wrap_breaks("", 4), vec![0]);
+        assert_eq!(wrap_breaks("abcdefghij", 4), vec![0, 4, 8]);
+        // Wide chars take two columns and never straddle a row
+        assert_eq!(wrap_breaks("日本語です", 4), vec![0, 2, 4]);
+        assert_eq!(wrap_breaks("a日本", 4), vec![0, 2]);
+        assert_eq!(wrap_breaks("😀😀😀", 5), vec![0, 2]);
+        // A char wider than the pane still gets a row of its own
+        assert_eq!(wrap_breaks("日本", 1), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_fold_keeps_context_around_changes() {
+        let numbers: String = (0..21).map(|i| format!("{}\n", i)).collect();
+        let diff_ops = vec![
+            DiffOp::Equal { old_index: 0, new_index: 0, len: 10 },
+            DiffOp::Replace { old_index: 10, old_len: 1, new_index: 10, new_len: 1 },
+            DiffOp::Equal { old_index: 11, new_index: 11, len: 10 },
+        ];
+        let mut app = test_app(diff_ops, view(&numbers), view(&numbers));
+        app.context = 2;
+        assert_eq!(app.total_rows(), 21);
+
+        // Leading run: fold row + 2 context lines; trailing run: 2 context lines + fold row
+        app.toggle_fold();
+        assert_eq!(app.op_row_counts, vec![0, 3, 4]);
+        assert_eq!(app.total_rows(), 7);
+        assert_eq!(app.row_lines(0), (None, None));
+        assert_eq!(app.row_lines(1), (Some(8), Some(8)));
+        assert_eq!(app.row_lines(5), (Some(12), Some(12)));
+        assert_eq!(app.row_of(2, 9), 6);
+
+        // Revealing a hidden line unfolds only its run
+        app.reveal(0, 4);
+        assert_eq!(app.op_row_counts, vec![0, 10, 11]);
+        assert_eq!((app.scroll_offset, app.row_lines(app.scroll_offset).0), (4, Some(4)));
+    }
+
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(12345), "12,345");
+        assert_eq!(group_thousands(1_000_000), "1,000,000");
+    }
+
+    #[test]
+    fn test_unified_layout_stacks_replaced_lines() {
+        let diff_ops = vec![
+            DiffOp::Equal { old_index: 0, new_index: 0, len: 2 },
+            DiffOp::Replace { old_index: 2, old_len: 2, new_index: 2, new_len: 3 },
+            DiffOp::Equal { old_index: 4, new_index: 5, len: 1 },
+        ];
+        let mut app = test_app(diff_ops, view("a\nb\nc\nd\ne"), view("a\nb\nC\nD\nX\ne"));
+        app.scroll_to(3);
+        app.toggle_unified();
+
+        // Removed lines first, then added ones; the top line stays in place
+        assert_eq!(app.op_row_counts, vec![0, 2, 7]);
+        let rows: Vec<_> = (2..7).map(|row| app.row_lines(row)).collect();
+        assert_eq!(rows, vec![(Some(2), None), (Some(3), None), (None, Some(2)), (None, Some(3)), (None, Some(4))]);
+        assert_eq!(app.scroll_offset, 3);
+        assert_eq!(app.op_of_line(false, 3), Some((1, 3)));
+
+        app.toggle_unified();
+        assert_eq!((app.op_row_counts.clone(), app.total_rows()), (vec![0, 2, 5], 6));
+    }
+
+    #[test]
+    fn test_undo_redo_resolutions() {
+        let diff_ops = vec![
+            DiffOp::Replace { old_index: 0, old_len: 1, new_index: 0, new_len: 1 },
+            DiffOp::Equal { old_index: 1, new_index: 1, len: 1 },
+            DiffOp::Insert { old_index: 2, new_index: 2, new_len: 1 },
+        ];
+        let mut app = test_app(diff_ops, None, None);
+
+        app.resolve(0, Resolution::PickRight);
+        app.resolve(0, Resolution::PickRight); // No change, no history entry
+        app.resolve_many("Pick Left on all".to_string(), vec![(0, Resolution::PickLeft), (2, Resolution::PickLeft)]);
+        assert_eq!(app.undo_stack.len(), 2);
+
+        app.undo(false);
+        assert_eq!(app.resolutions, vec![Resolution::PickRight, Resolution::Unresolved, Resolution::Unresolved]);
+        assert_eq!(app.status.as_deref(), Some("Undid: Pick Left on all"));
+        app.undo(false);
+        assert_eq!(app.resolutions[0], Resolution::Unresolved);
+        app.undo(false);
+        assert_eq!(app.status.as_deref(), Some("Nothing to undo"));
+
+        app.undo(true);
+        assert_eq!((app.resolutions[0].clone(), app.status.as_deref()), (Resolution::PickRight, Some("Redid: Pick Right on diff 1")));
+        // A new change drops what could still be redone
+        app.resolve(2, Resolution::PickBoth);
+        app.undo(true);
+        assert_eq!(app.status.as_deref(), Some("Nothing to redo"));
+    }
+
+    #[test]
+    fn test_bulk_plan_targets() {
+        let filler: Vec<u32> = (0..20).collect();
+        let f1 = pretty(serde_json::json!({"a": {"x": 1, "y": 1}, "m": filler, "z": {"x": 1}}));
+        let f2 = pretty(serde_json::json!({"a": {"x": 2, "y": 2}, "m": filler, "z": {"x": 2}}));
+        let diff_ops = line_diff(&f1, &f2);
+        let changed: Vec<usize> = (0..diff_ops.len()).filter(|&i| !matches!(diff_ops[i], DiffOp::Equal { .. })).collect();
+        let mut app = test_app(diff_ops, f1, f2);
+        assert_eq!(changed.len(), 2);
+        app.resolutions[changed[1]] = Resolution::PickLeft;
+
+        let (label, targets) = app.bulk_plan(&BulkTarget::Unresolved, Resolution::PickRight);
+        assert_eq!((label.as_str(), targets), ("Pick Right on 1 diff unresolved", vec![(changed[0], Resolution::PickRight)]));
+
+        // The long array between the two changes splits them into separate regions
+        let (_, targets) = app.bulk_plan(&BulkTarget::Region(app.region_of(changed[1])), Resolution::PickBoth);
+        assert_eq!(targets, vec![(changed[1], Resolution::PickBoth)]);
+
+        let pattern: PathPattern = "$.z".parse().unwrap();
+        let (label, targets) = app.bulk_plan(&BulkTarget::Path("$.z".to_string(), pattern), Resolution::PickRight);
+        assert_eq!((label.as_str(), targets.len()), ("Pick Right on 1 diff under $.z", 1));
+        app.resolve_many(label, targets);
+        assert_eq!(app.resolutions[changed[1]], Resolution::PickRight);
+    }
+
+    #[test]
+    fn test_check_merged_reports_syntax_errors() {
+        let (f1, f2) = (view("{\n  \"a\": 1,\n  \"b\": 2\n}\n"), view("{\n  \"a\": 1,\n  \"b\": 3\n}\n"));
+        let diff_ops = line_diff(&f1, &f2);
+        let replaced = diff_ops.iter().position(|op| matches!(op, DiffOp::Replace { .. })).unwrap();
+        let mut app = test_app(diff_ops, f1, f2);
+        app.toggle_unified();
+        assert_eq!(write_merged(&app, &mut io::sink()).unwrap(), None);
+
+        // Both members end up without a comma between them
+        app.resolutions[replaced] = Resolution::PickBoth;
+        let mut out = Vec::new();
+        let error = write_merged(&app, &mut out).unwrap().unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "{\n  \"a\": 1,\n  \"b\": 2\n  \"b\": 3\n}\n");
+        assert_eq!((error.line, error.column, error.tag), (4, 3, replaced));
+
+        // The offending line is the added one, listed after the removed one
+        app.check_merged();
+        assert_eq!(app.selected_op_index, Some(replaced));
+        assert_eq!(app.scroll_offset, app.row_of(replaced, 1));
+        assert_eq!(app.status.as_deref(), Some(format!("Invalid merged JSON: expected `,` or `}}` at line 4, column 3 (diff {})", replaced + 1).as_str()));
+    }
+
+    #[test]
+    fn test_search_steps_through_hits() {
+        let diff_ops = vec![
+            DiffOp::Equal { old_index: 0, new_index: 0, len: 2 },
+            DiffOp::Replace { old_index: 2, old_len: 1, new_index: 2, new_len: 1 },
+            DiffOp::Equal { old_index: 3, new_index: 3, len: 2 },
+        ];
+        let mut app = test_app(diff_ops, view("key\nx\nold key\ny\nkey\n"), view("key\nx\nnew\ny\nkey\n"));
+        app.scroll_to(1);
+
+        app.start_search("key".to_string());
+        while app.search_rx.is_some() {
+            app.poll_search();
+        }
+        // First hit at or below the view is the replaced row, which gets selected
+        assert_eq!(app.search.as_ref().map(|s| s.hits.len()), Some(3));
+        assert_eq!((app.scroll_offset, app.selected_op_index), (2, Some(1)));
+
+        app.step_search(true);
+        assert_eq!(app.scroll_offset, 4);
+        app.step_search(true);
+        assert_eq!(app.scroll_offset, 0);
+        app.step_search(false);
+        assert_eq!(app.scroll_offset, 4);
+
+        app.clear_search();
+        assert!(app.search.is_none());
+    }
+
+    #[test]
+    fn test_conflict_navigation_after_search() {
+        let diff_ops = vec![
+            DiffOp::Replace { old_index: 0, old_len: 1, new_index: 0, new_len: 1 },
+            DiffOp::Equal { old_index: 1, new_index: 1, len: 1 },
+            DiffOp::Replace { old_index: 2, old_len: 1, new_index: 2, new_len: 1 },
+            DiffOp::Equal { old_index: 3, new_index: 3, len: 1 },
+            DiffOp::Delete { old_index: 4, old_len: 1, new_index: 4 },
+        ];
+        let mut app = test_app(diff_ops, view("a\nkey\nb\nc\nd\n"), view("x\nkey\ny\nc\n"));
+
+        app.start_search("key".to_string());
+        while app.search_rx.is_some() {
+            app.poll_search();
+        }
+        assert_eq!(app.search.as_ref().map(|s| s.hits.len()), Some(1));
+        assert_eq!(app.selected_op_index, None);
+
+        // n steps matches while the search is active
+        app.step_search(true);
+        assert_eq!(app.scroll_offset, 1);
+        assert_eq!(app.status.as_deref(), Some("Match 1/1"));
+
+        // Esc clears it, and n / p go back to moving between conflicts
+        app.clear_search();
+        assert!(app.search.is_none() && app.search_rx.is_none());
+        app.step_conflict(true);
+        assert_eq!((app.selected_op_index, app.scroll_offset), (Some(0), 0));
+        app.step_conflict(true);
+        assert_eq!((app.selected_op_index, app.scroll_offset), (Some(2), 2));
+        app.step_conflict(true);
+        assert_eq!((app.selected_op_index, app.scroll_offset), (Some(4), 4));
+        app.step_conflict(false);
+        assert_eq!(app.selected_op_index, Some(2));
+    }
+
+    #[test]
+    fn test_inline_spans_are_cached_per_mode() {
+        let diff_ops = vec![
+            DiffOp::Equal { old_index: 0, new_index: 0, len: 1 },
+            DiffOp::Replace { old_index: 1, old_len: 2, new_index: 1, new_len: 2 },
+        ];
+        let mut app = test_app(diff_ops, view("same\nalpha beta\nkeep\n"), view("same\nalpha gamma\nkeep\n"));
+        let mut terminal = Terminal::new(backend::TestBackend::new(60, 12)).unwrap();
+
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        // Only the line that differs is refined
+        let word = inline::changed_spans("alpha beta", "alpha gamma", inline::Granularity::Word);
+        assert_eq!(app.inline_spans.keys().collect::<Vec<_>>(), vec![&(1, 0)]);
+        assert_eq!(app.inline_spans[&(1, 0)], word);
+
+        // Later frames reuse the cached spans instead of diffing again
+        app.inline_spans.insert((1, 0), (Vec::new(), Vec::new()));
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        assert_eq!(app.inline_spans[&(1, 0)], (Vec::new(), Vec::new()));
+
+        app.cycle_inline_mode();
+        assert!(app.inline_spans.is_empty());
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        assert_eq!(app.inline_spans[&(1, 0)], inline::changed_spans("alpha beta", "alpha gamma", inline::Granularity::Char));
+        assert_ne!(app.inline_spans[&(1, 0)], word);
+    }
+
+    #[test]
+    fn test_goto_path_scrolls_and_selects() {
+        let f1 = pretty(serde_json::json!({"a": 1, "b": {"c": [true, false]}}));
+        let f2 = pretty(serde_json::json!({"a": 1, "b": {"c": [true, true]}, "d": 0}));
+        let diff_ops = line_diff(&f1, &f2);
+        let mut app = test_app(diff_ops, f1, f2);
+
+        // $.b.c[1] is the changed line 6 on both sides
+        app.goto_path("$.b.c[1]");
+        assert_eq!(app.scroll_offset, 5);
+        assert!(matches!(app.selected_op_index.map(|i| app.diff_ops[i]), Some(DiffOp::Replace { .. })));
+        let (l, r) = app.focus_lines(20);
+        assert_eq!(json_path_at(app.file1.as_deref(), l).as_deref(), Some("$.b.c[1]"));
+        assert_eq!(json_path_at(app.file2.as_deref(), r).as_deref(), Some("$.b.c[1]"));
+
+        // Only on the right: falls back to that side
+        app.goto_path("/d");
+        assert_eq!(app.row_lines(app.scroll_offset).1, Some(8));
+
+        app.goto_path("$.missing");
+        assert_eq!(app.status.as_deref(), Some("$.missing not found"));
+    }
+
     #[test]
     fn test_save_prompt_flow() -> Result<()> {
         let diff_ops = vec![DiffOp::Equal { old_index: 0, new_index: 0, len: 1 }];
@@ -1336,17 +3513,10 @@ mod tests {
             state: AppState::Done,
             diff_ops,
             op_row_counts: vec![0],
-            file1: Some(f1),
-            file2: Some(f2),
-            scroll_offset: 0,
-            scroll_state: ScrollbarState::default(),
-            spinner_index: 0,
-            receiver: std::sync::mpsc::channel().1,
-            file1_name: "f1".to_string(),
-            file2_name: "f2".to_string(),
-            loading_log: String::new(),
+            file1: Some(Arc::new(f1)),
+            file2: Some(Arc::new(f2)),
             resolutions: vec![Resolution::Unresolved],
-            selected_op_index: None,
+            ..App::new(std::sync::mpsc::channel().1, "f1".to_string(), "f2".to_string())
         };
 
         // 1. Initial State
@@ -1377,7 +3547,81 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_file(custom_path);
         let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(path2);
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_unresolved_count_ignores_equal_ops() {
+        let diff_ops = vec![
+            DiffOp::Equal { old_index: 0, new_index: 0, len: 1 },
+            DiffOp::Replace { old_index: 1, old_len: 1, new_index: 1, new_len: 1 },
+            DiffOp::Insert { old_index: 2, new_index: 2, new_len: 1 },
+        ];
+        let mut app = test_app(diff_ops, None, None);
+        app.resolutions[1] = Resolution::PickRight;
+        assert_eq!(app.unresolved_count(), 1);
+    }
+
+    #[test]
+    fn test_patch_export_diffs_raw_lines_of_reformatted_json() -> Result<()> {
+        let (p1, p2, out) = (PathBuf::from("test_raw_patch_1.json"), PathBuf::from("test_raw_patch_2.json"), "test_raw_patch.diff");
+        std::fs::write(&p1, "{\"a\":1,\"b\":[1,2]}\nnext\n")?;
+        std::fs::write(&p2, "{\"a\":1,\"b\":[1,3]}\nnext\n")?;
+        let (f1, f2) = (LazyDiffView::new(&p1)?, LazyDiffView::new(&p2)?);
+        let mut app = test_app(line_diff(&f1, &f2), Arc::new(f1), Arc::new(f2));
+        app.setup = Some(session::Setup {
+            base: None,
+            left: p1.clone(),
+            right: p2.clone(),
+            merged: None,
+            labels: Vec::new(),
+            options: session::Options::default(),
+            started: SystemTime::now(),
+        });
+        app.patch_labels = ("a/x.json".to_string(), "b/x.json".to_string());
+
+        // Not JSON as a whole, so not reformatted: the ops shown are used as they are
+        let plain = export_patch(&app, out).map(|()| std::fs::read_to_string(out));
+        std::fs::write(&p1, "{\"a\":1,\"b\":[1,2]}\n")?;
+        std::fs::write(&p2, "{\"a\":1,\"b\":[1,3]}\n")?;
+        let (f1, f2) = (LazyDiffView::new(&p1)?, LazyDiffView::new(&p2)?);
+        assert!(f1.reformatted && f2.reformatted);
+        app.diff_ops = line_diff(&f1, &f2);
+        app.file1 = Some(Arc::new(f1));
+        app.file2 = Some(Arc::new(f2));
+        let raw = export_patch(&app, out).map(|()| std::fs::read_to_string(out));
+
+        let _ = std::fs::remove_file(&p1);
+        let _ = std::fs::remove_file(&p2);
+        let _ = std::fs::remove_file(out);
+        assert!(plain??.contains("-{\"a\":1,\"b\":[1,2]}\n+{\"a\":1,\"b\":[1,3]}\n next\n"));
+        // The pretty-printed lines shown would not apply to the minified files
+        assert_eq!(raw??, "--- a/x.json\n+++ b/x.json\n@@ -1 +1 @@\n-{\"a\":1,\"b\":[1,2]}\n+{\"a\":1,\"b\":[1,3]}\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_streaming_refuses_array_options() {
+        let (small, large) = (PathBuf::from("test_stream_opts_1.json"), PathBuf::from("test_stream_opts_2.json"));
+        std::fs::write(&small, "[]").unwrap();
+        // Sparse, so nothing is actually written
+        File::create(&large).unwrap().set_len(MAX_JSON_FORMAT_SIZE + 1).unwrap();
+
+        let ignore_order = DiffOptions { ignore_order: true, ..Default::default() };
+        let by_position = check_streaming_options([&small, &small], &ignore_order);
+        let streamed = check_streaming_options([&small, &large], &ignore_order);
+        // A merge base counts like the two sides
+        let base = check_streaming_options([&large, &small, &small], &ignore_order);
+        let defaults = check_streaming_options([&small, &large], &DiffOptions::default());
+
+        let _ = std::fs::remove_file(&small);
+        let _ = std::fs::remove_file(&large);
+        assert!(by_position.is_ok());
+        let err = streamed.unwrap_err().to_string();
+        assert!(err.contains("not supported in streaming mode (test_stream_opts_2.json is larger than 300 MB)"), "{}", err);
+        assert!(base.is_err());
+        assert!(defaults.is_ok());
+    }
+}
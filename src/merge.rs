@@ -0,0 +1,179 @@
+use crate::{LazyDiffView, Resolution};
+use similar::DiffOp;
+use std::ops::Range;
+
+// --- THREE-WAY MERGE (diff3) ---
+// Both sides are diffed against BASE. Change hunks that overlap (or touch) in
+// base coordinates form one region; a region changed by a single side is
+// pre-resolved to that side, a region changed identically by both is equal,
+// and anything else stays Unresolved.
+
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Ours,
+    Theirs,
+}
+
+struct Hunk {
+    side: Side,
+    base: Range<usize>,
+    lines: Range<usize>,
+}
+
+fn hunks(ops: &[DiffOp], side: Side) -> impl Iterator<Item = Hunk> + '_ {
+    ops.iter().filter_map(move |op| match *op {
+        DiffOp::Equal { .. } => None,
+        DiffOp::Delete { old_index, old_len, new_index } => Some(Hunk { side, base: old_index..old_index + old_len, lines: new_index..new_index }),
+        DiffOp::Insert { old_index, new_index, new_len } => Some(Hunk { side, base: old_index..old_index, lines: new_index..new_index + new_len }),
+        DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+            Some(Hunk { side, base: old_index..old_index + old_len, lines: new_index..new_index + new_len })
+        }
+    })
+}
+
+/// Range of one side covering base lines `lo..hi`. `delta` is that side's
+/// line offset relative to base after the region.
+fn side_range(group: &[&Hunk], side: Side, lo: usize, hi: usize, delta: &mut isize) -> Range<usize> {
+    let mut own = group.iter().filter(|h| h.side == side);
+    match (own.next(), own.next_back()) {
+        (Some(first), last) => {
+            let last = last.unwrap_or(first);
+            *delta = last.lines.end as isize - last.base.end as isize;
+            (first.lines.start - (first.base.start - lo))..(last.lines.end + (hi - last.base.end))
+        }
+        _ => ((lo as isize + *delta) as usize)..((hi as isize + *delta) as usize),
+    }
+}
+
+fn same_lines(ours: &LazyDiffView, a: &Range<usize>, theirs: &LazyDiffView, b: &Range<usize>) -> bool {
+    a.len() == b.len() && a.clone().zip(b.clone()).all(|(i, j)| ours.get_line(i) == theirs.get_line(j))
+}
+
+/// Produces OURS→THEIRS ops plus one resolution per op from the BASE→OURS and
+/// BASE→THEIRS line diffs.
+pub fn merge_ops(
+    base_to_ours: &[DiffOp],
+    base_to_theirs: &[DiffOp],
+    ours: &LazyDiffView,
+    theirs: &LazyDiffView,
+    base_len: usize,
+) -> (Vec<DiffOp>, Vec<Resolution>) {
+    let mut all: Vec<Hunk> = hunks(base_to_ours, Side::Ours).chain(hunks(base_to_theirs, Side::Theirs)).collect();
+    all.sort_by_key(|h| (h.base.start, h.base.end));
+
+    let mut ops = Vec::new();
+    let mut resolutions = Vec::new();
+    let (mut base_pos, mut d_ours, mut d_theirs) = (0usize, 0isize, 0isize);
+
+    let mut i = 0;
+    while i < all.len() {
+        // Grow the region while the next hunk overlaps or touches it
+        let lo = all[i].base.start;
+        let mut hi = all[i].base.end;
+        let mut j = i + 1;
+        while j < all.len() && all[j].base.start <= hi {
+            hi = hi.max(all[j].base.end);
+            j += 1;
+        }
+        let group: Vec<&Hunk> = all[i..j].iter().collect();
+
+        if lo > base_pos {
+            ops.push(DiffOp::Equal {
+                old_index: (base_pos as isize + d_ours) as usize,
+                new_index: (base_pos as isize + d_theirs) as usize,
+                len: lo - base_pos,
+            });
+            resolutions.push(Resolution::Unresolved);
+        }
+
+        let ours_changed = group.iter().any(|h| h.side == Side::Ours);
+        let theirs_changed = group.iter().any(|h| h.side == Side::Theirs);
+        let a = side_range(&group, Side::Ours, lo, hi, &mut d_ours);
+        let b = side_range(&group, Side::Theirs, lo, hi, &mut d_theirs);
+
+        let resolution = match (ours_changed, theirs_changed) {
+            (true, false) => Some(Resolution::PickLeft),
+            (false, true) => Some(Resolution::PickRight),
+            _ if same_lines(ours, &a, theirs, &b) => None,
+            _ => Some(Resolution::Unresolved),
+        };
+
+        match resolution {
+            // Both sides made the same change
+            None => {
+                if !a.is_empty() {
+                    ops.push(DiffOp::Equal { old_index: a.start, new_index: b.start, len: a.len() });
+                    resolutions.push(Resolution::Unresolved);
+                }
+            }
+            Some(resolution) => {
+                ops.push(if a.is_empty() {
+                    DiffOp::Insert { old_index: a.start, new_index: b.start, new_len: b.len() }
+                } else if b.is_empty() {
+                    DiffOp::Delete { old_index: a.start, old_len: a.len(), new_index: b.start }
+                } else {
+                    DiffOp::Replace { old_index: a.start, old_len: a.len(), new_index: b.start, new_len: b.len() }
+                });
+                resolutions.push(resolution);
+            }
+        }
+
+        base_pos = hi;
+        i = j;
+    }
+
+    // Trailing stable region
+    let ours_start = (base_pos as isize + d_ours) as usize;
+    let theirs_start = (base_pos as isize + d_theirs) as usize;
+    let len = (base_len - base_pos).min(ours.len().saturating_sub(ours_start)).min(theirs.len().saturating_sub(theirs_start));
+    if len > 0 {
+        ops.push(DiffOp::Equal { old_index: ours_start, new_index: theirs_start, len });
+        resolutions.push(Resolution::Unresolved);
+    }
+
+    (ops, resolutions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{line_diff, ContentSource};
+
+    fn view(text: &str) -> LazyDiffView {
+        LazyDiffView::from_source(ContentSource::Memory(text.as_bytes().to_vec())).unwrap()
+    }
+
+    #[test]
+    fn test_one_sided_changes_are_pre_resolved() {
+        let base = view("a\nb\nc\nd\ne\nf\ng\n");
+        let ours = view("a\nB\nc\nd\ne\nF\ng\n");
+        let theirs = view("a\nb\nc\nD\ne\nf2\ng\nh\n");
+
+        let (ops, resolutions) = merge_ops(&line_diff(&base, &ours), &line_diff(&base, &theirs), &ours, &theirs, base.len());
+        let changed: Vec<(DiffOp, Resolution)> = ops
+            .into_iter()
+            .zip(resolutions)
+            .filter(|(op, _)| !matches!(op, DiffOp::Equal { .. }))
+            .collect();
+
+        assert_eq!(
+            changed,
+            vec![
+                (DiffOp::Replace { old_index: 1, old_len: 1, new_index: 1, new_len: 1 }, Resolution::PickLeft),
+                (DiffOp::Replace { old_index: 3, old_len: 1, new_index: 3, new_len: 1 }, Resolution::PickRight),
+                (DiffOp::Replace { old_index: 5, old_len: 1, new_index: 5, new_len: 1 }, Resolution::Unresolved),
+                (DiffOp::Insert { old_index: 7, new_index: 7, new_len: 1 }, Resolution::PickRight),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_identical_changes_merge_cleanly() {
+        let base = view("a\nb\nc\n");
+        let ours = view("a\nX\nc\n");
+        let theirs = view("a\nX\nc\n");
+
+        let (ops, _) = merge_ops(&line_diff(&base, &ours), &line_diff(&base, &theirs), &ours, &theirs, base.len());
+        assert!(ops.iter().all(|op| matches!(op, DiffOp::Equal { .. })));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::structural::{ChangeKind, PathSegment, StructuralChange};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+// --- RFC 6902 JSON PATCH ---
+
+/// Turns structural changes into an RFC 6902 patch that transforms the old
+/// document into the new one.
+///
+/// A removed object member whose exact value reappears elsewhere becomes a
+/// single `move`. Removals at the tail of an array are emitted highest index
+/// first so every pointer is valid at the moment its operation is applied.
+pub fn to_json_patch(changes: &[StructuralChange]) -> Value {
+    // Candidate move sources: removed object members, keyed by their value
+    let mut removed_members: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, change) in changes.iter().enumerate() {
+        if let (ChangeKind::Removed(v), Some(PathSegment::Key(_))) = (&change.kind, change.path.0.last()) {
+            removed_members.entry(v.to_string()).or_default().push(i);
+        }
+    }
+
+    let mut move_from: HashMap<usize, usize> = HashMap::new(); // added idx -> removed idx
+    let mut moved_away = vec![false; changes.len()];
+    for (i, change) in changes.iter().enumerate() {
+        if let ChangeKind::Added(v) = &change.kind {
+            if let Some(sources) = removed_members.get_mut(&v.to_string()) {
+                if let Some(src) = sources.pop() {
+                    move_from.insert(i, src);
+                    moved_away[src] = true;
+                }
+            }
+        }
+    }
+
+    let mut ops = Vec::new();
+    let mut i = 0;
+    while i < changes.len() {
+        let change = &changes[i];
+        match &change.kind {
+            ChangeKind::Removed(_) if moved_away[i] => {}
+            ChangeKind::Removed(_) => {
+                // Collect the run of removals from the same array and reverse it
+                let mut end = i + 1;
+                if matches!(change.path.0.last(), Some(PathSegment::Index(_))) {
+                    while end < changes.len()
+                        && matches!(changes[end].kind, ChangeKind::Removed(_))
+                        && matches!(changes[end].path.0.last(), Some(PathSegment::Index(_)))
+                        && same_parent(&changes[end], change)
+                    {
+                        end += 1;
+                    }
+                }
+                for c in changes[i..end].iter().rev() {
+                    ops.push(json!({ "op": "remove", "path": c.path.to_pointer() }));
+                }
+                i = end;
+                continue;
+            }
+            ChangeKind::Added(v) => match move_from.get(&i) {
+                Some(&src) => ops.push(json!({
+                    "op": "move",
+                    "from": changes[src].path.to_pointer(),
+                    "path": change.path.to_pointer(),
+                })),
+                None => ops.push(json!({ "op": "add", "path": change.path.to_pointer(), "value": v })),
+            },
+            ChangeKind::Changed { new, .. } | ChangeKind::TypeChanged { new, .. } => {
+                ops.push(json!({ "op": "replace", "path": change.path.to_pointer(), "value": new }));
+            }
+            // Only produced with --array-key; exact patches are generated without it
+            ChangeKind::Moved { from } => {
+                ops.push(json!({ "op": "move", "from": from.to_pointer(), "path": change.path.to_pointer() }));
+            }
+        }
+        i += 1;
+    }
+    Value::Array(ops)
+}
+
+fn same_parent(a: &StructuralChange, b: &StructuralChange) -> bool {
+    let (pa, pb) = (&a.path.0, &b.path.0);
+    pa.len() == pb.len() && pa[..pa.len() - 1] == pb[..pb.len() - 1]
+}
+
+// --- PATCH APPLICATION (RFC 6902 / RFC 7396) ---
+
+#[derive(Debug, PartialEq)]
+pub struct PatchError {
+    pub index: usize,
+    pub op: String,
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "operation {} ({} \"{}\"): {}", self.index, self.op, self.pointer, self.message)
+    }
+}
+
+/// Applies an RFC 6902 patch in place. A failed operation does not stop the
+/// run, so every broken operation is reported at once.
+pub fn apply_json_patch(doc: &mut Value, patch: &Value) -> Result<(), Vec<PatchError>> {
+    let ops = match patch.as_array() {
+        Some(ops) => ops,
+        None => {
+            return Err(vec![PatchError {
+                index: 0,
+                op: String::new(),
+                pointer: String::new(),
+                message: "a JSON Patch must be an array of operations".to_string(),
+            }])
+        }
+    };
+
+    let mut errors = Vec::new();
+    for (index, op) in ops.iter().enumerate() {
+        let name = op.get("op").and_then(Value::as_str).unwrap_or_default();
+        let pointer = op.get("path").and_then(Value::as_str).unwrap_or_default();
+        if let Err(message) = apply_operation(doc, op) {
+            errors.push(PatchError { index, op: name.to_string(), pointer: pointer.to_string(), message });
+        }
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+fn apply_operation(doc: &mut Value, op: &Value) -> Result<(), String> {
+    let name = op.get("op").and_then(Value::as_str).ok_or("missing \"op\"")?;
+    let path = parse_pointer(op.get("path").and_then(Value::as_str).ok_or("missing \"path\"")?)?;
+    let value = || op.get("value").cloned().ok_or_else(|| "missing \"value\"".to_string());
+    let from = || -> Result<Vec<String>, String> {
+        parse_pointer(op.get("from").and_then(Value::as_str).ok_or("missing \"from\"")?)
+    };
+
+    match name {
+        "add" => add(doc, &path, value()?),
+        "remove" => remove(doc, &path).map(|_| ()),
+        "replace" => {
+            let target = resolve_mut(doc, &path)?;
+            *target = value()?;
+            Ok(())
+        }
+        "move" => {
+            let from = from()?;
+            if path.len() > from.len() && path[..from.len()] == from[..] {
+                return Err("cannot move a value into one of its own children".to_string());
+            }
+            let moved = remove(doc, &from)?;
+            add(doc, &path, moved)
+        }
+        "copy" => {
+            let copied = resolve_mut(doc, &from()?)?.clone();
+            add(doc, &path, copied)
+        }
+        "test" => {
+            if *resolve_mut(doc, &path)? == value()? {
+                Ok(())
+            } else {
+                Err("test failed: value differs".to_string())
+            }
+        }
+        other => Err(format!("unknown operation \"{}\"", other)),
+    }
+}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(format!("invalid JSON Pointer \"{}\"", pointer));
+    }
+    Ok(pointer[1..].split('/').map(|t| t.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+fn array_index(token: &str, len: usize) -> Result<usize, String> {
+    let idx: usize = token.parse().map_err(|_| format!("invalid array index \"{}\"", token))?;
+    if idx >= len {
+        return Err(format!("index {} out of bounds (length {})", idx, len));
+    }
+    Ok(idx)
+}
+
+fn resolve_mut<'a>(doc: &'a mut Value, tokens: &[String]) -> Result<&'a mut Value, String> {
+    let mut current = doc;
+    for token in tokens {
+        current = match current {
+            Value::Object(map) => map.get_mut(token).ok_or_else(|| format!("key \"{}\" not found", token))?,
+            Value::Array(arr) => {
+                let idx = array_index(token, arr.len())?;
+                &mut arr[idx]
+            }
+            _ => return Err(format!("cannot index into a scalar with \"{}\"", token)),
+        };
+    }
+    Ok(current)
+}
+
+fn add(doc: &mut Value, tokens: &[String], value: Value) -> Result<(), String> {
+    let Some((last, parent_tokens)) = tokens.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+    match resolve_mut(doc, parent_tokens)? {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+            Ok(())
+        }
+        Value::Array(arr) => {
+            if last == "-" {
+                arr.push(value);
+            } else {
+                let idx: usize = last.parse().map_err(|_| format!("invalid array index \"{}\"", last))?;
+                if idx > arr.len() {
+                    return Err(format!("index {} out of bounds (length {})", idx, arr.len()));
+                }
+                arr.insert(idx, value);
+            }
+            Ok(())
+        }
+        _ => Err("parent is not an object or array".to_string()),
+    }
+}
+
+fn remove(doc: &mut Value, tokens: &[String]) -> Result<Value, String> {
+    let Some((last, parent_tokens)) = tokens.split_last() else {
+        return Err("cannot remove the document root".to_string());
+    };
+    match resolve_mut(doc, parent_tokens)? {
+        Value::Object(map) => map.remove(last).ok_or_else(|| format!("key \"{}\" not found", last)),
+        Value::Array(arr) => {
+            let idx = array_index(last, arr.len())?;
+            Ok(arr.remove(idx))
+        }
+        _ => Err("parent is not an object or array".to_string()),
+    }
+}
+
+/// Applies an RFC 7396 merge patch in place.
+pub fn apply_merge_patch(doc: &mut Value, patch: &Value) {
+    let Value::Object(patch_map) = patch else {
+        *doc = patch.clone();
+        return;
+    };
+    if !doc.is_object() {
+        *doc = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = doc {
+        for (k, v) in patch_map {
+            if v.is_null() {
+                map.remove(k);
+            } else {
+                apply_merge_patch(map.entry(k.clone()).or_insert(Value::Null), v);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structural::{diff_values, DiffOptions};
+
+    #[test]
+    fn test_patch_ops() {
+        let a = json!({"keep": 1, "old_name": {"x": [1, 2]}, "list": [1, 2, 3, 4], "n": 1});
+        let b = json!({"keep": 1, "new_name": {"x": [1, 2]}, "list": [1, 5], "n": "one", "extra": true});
+        let patch = to_json_patch(&diff_values(&a, &b, &DiffOptions::default()));
+
+        assert_eq!(
+            patch,
+            json!([
+                { "op": "replace", "path": "/list/1", "value": 5 },
+                { "op": "remove", "path": "/list/3" },
+                { "op": "remove", "path": "/list/2" },
+                { "op": "replace", "path": "/n", "value": "one" },
+                { "op": "add", "path": "/extra", "value": true },
+                { "op": "move", "from": "/old_name", "path": "/new_name" },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_generated_patch_round_trips() {
+        let a = json!({"a": {"b": 1}, "list": [1, 2, 3], "gone": "x"});
+        let b = json!({"a": {"b": 2, "c": [true]}, "list": [0], "moved": "x"});
+        let mut doc = a.clone();
+        apply_json_patch(&mut doc, &to_json_patch(&diff_values(&a, &b, &DiffOptions::default()))).unwrap();
+        assert_eq!(doc, b);
+    }
+
+    #[test]
+    fn test_apply_reports_every_failed_operation() {
+        let mut doc = json!({"a": [1, 2]});
+        let patch = json!([
+            { "op": "remove", "path": "/missing" },
+            { "op": "add", "path": "/a/-", "value": 3 },
+            { "op": "replace", "path": "/a/9", "value": 0 },
+            { "op": "test", "path": "/a/2", "value": 3 },
+        ]);
+        let errors = apply_json_patch(&mut doc, &patch).unwrap_err();
+        let summary: Vec<(usize, &str)> = errors.iter().map(|e| (e.index, e.pointer.as_str())).collect();
+        assert_eq!(summary, vec![(0, "/missing"), (2, "/a/9")]);
+        assert_eq!(errors[1].to_string(), "operation 2 (replace \"/a/9\"): index 9 out of bounds (length 3)");
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let mut doc = json!({"title": "Goodbye!", "author": {"givenName": "John", "familyName": "Doe"}, "tags": ["a", "b"]});
+        apply_merge_patch(&mut doc, &json!({"title": "Hello!", "author": {"familyName": null}, "tags": ["c"], "phone": "555"}));
+        assert_eq!(doc, json!({"title": "Hello!", "author": {"givenName": "John"}, "tags": ["c"], "phone": "555"}));
+    }
+}
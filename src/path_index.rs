@@ -0,0 +1,187 @@
+use crate::structural::{JsonPath, PathSegment};
+use crate::LazyDiffView;
+
+// --- JSON PATH <-> LINE INDEX ---
+// Built over our own pretty-printed output, where every value starts on its
+// own line, containers open at the end of a line and close on a line of their
+// own. Per line we keep the enclosing container's line and the array position,
+// so resolving a path only walks the containers along it.
+
+const ROOT: u32 = u32::MAX;
+// Set on closing lines, which point at the line they close
+const CLOSER: u32 = 1 << 31;
+
+pub struct PathIndex {
+    parent: Vec<u32>,
+    // Position within the enclosing array (0 for object members)
+    slot: Vec<u32>,
+}
+
+impl PathIndex {
+    /// Indexes a view holding pretty-printed JSON. None if the layout is off.
+    pub fn build(view: &LazyDiffView) -> Option<Self> {
+        let n = view.len();
+        if n >= CLOSER as usize {
+            return None;
+        }
+        let mut parent = Vec::with_capacity(n);
+        let mut slot = Vec::with_capacity(n);
+        // (opening line, next array slot)
+        let mut stack: Vec<(u32, u32)> = Vec::new();
+
+        for i in 0..n {
+            let line = view.get_line(i)?.trim();
+            if line.is_empty() {
+                // Phantom line after a trailing newline
+                parent.push(ROOT);
+                slot.push(0);
+            } else if line.starts_with(['}', ']']) {
+                let (open, _) = stack.pop()?;
+                parent.push(CLOSER | open);
+                slot.push(0);
+            } else {
+                match stack.last_mut() {
+                    Some((open, next)) => {
+                        parent.push(*open);
+                        slot.push(*next);
+                        *next += 1;
+                    }
+                    None => {
+                        parent.push(ROOT);
+                        slot.push(0);
+                    }
+                }
+                if line.ends_with(['{', '[']) {
+                    stack.push((i as u32, 0));
+                }
+            }
+        }
+        stack.is_empty().then_some(Self { parent, slot })
+    }
+
+    /// Line where the value at `path` starts.
+    pub fn resolve(&self, view: &LazyDiffView, path: &[PathSegment]) -> Option<usize> {
+        let mut line = 0;
+        for segment in path {
+            let opener = view.get_line(line)?.trim_end();
+            let is_array = opener.ends_with('[');
+            if !is_array && !opener.ends_with('{') {
+                return None;
+            }
+            let container = line;
+            line = self
+                .children(container)
+                .find(|&child| match segment {
+                    PathSegment::Index(i) if is_array => self.slot[child] as usize == *i,
+                    // Pointer tokens and numeric object keys cross over
+                    PathSegment::Key(k) if is_array => k.parse() == Ok(self.slot[child] as usize),
+                    PathSegment::Key(k) => member_key(view, child).as_deref() == Some(k.as_str()),
+                    PathSegment::Index(i) => member_key(view, child) == Some(i.to_string()),
+                })?;
+        }
+        Some(line)
+    }
+
+    /// Path of the value on `line`; a closing line belongs to the value it closes.
+    pub fn path_of(&self, view: &LazyDiffView, line: usize) -> Option<JsonPath> {
+        let mut line = match *self.parent.get(line)? {
+            ROOT => return Some(JsonPath::root()),
+            p if p & CLOSER != 0 => (p & !CLOSER) as usize,
+            _ => line,
+        };
+        let mut segments = Vec::new();
+        while self.parent[line] != ROOT {
+            let container = self.parent[line] as usize;
+            segments.push(if view.get_line(container)?.ends_with('[') {
+                PathSegment::Index(self.slot[line] as usize)
+            } else {
+                PathSegment::Key(member_key(view, line)?)
+            });
+            line = container;
+        }
+        segments.reverse();
+        Some(JsonPath(segments))
+    }
+
+    // Lines of the direct children of the container opened on `container`
+    fn children(&self, container: usize) -> impl Iterator<Item = usize> + '_ {
+        let open = container as u32;
+        ((container + 1)..self.parent.len())
+            .take_while(move |&l| self.parent[l] != CLOSER | open)
+            .filter(move |&l| self.parent[l] == open)
+    }
+}
+
+/// Key of the object member starting on `line`.
+fn member_key(view: &LazyDiffView, line: usize) -> Option<String> {
+    let text = view.get_line(line)?.trim_start();
+    let bytes = text.as_bytes();
+    if bytes.first() != Some(&b'"') {
+        return None;
+    }
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => return serde_json::from_str(&text[..=i]).ok(),
+            b'\\' => i += 2,
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContentSource;
+    use serde_json::json;
+
+    fn pretty_view(value: serde_json::Value) -> LazyDiffView {
+        let text = serde_json::to_string_pretty(&value).unwrap();
+        LazyDiffView::from_source(ContentSource::Memory(text.into_bytes())).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_paths_to_lines() {
+        let view = pretty_view(json!({
+            "config": {"services": [{"env": "a"}, {"env": "b", "port": 80}], "empty": {}},
+            "x\"y": [1, [2, 3]],
+        }));
+        let index = PathIndex::build(&view).unwrap();
+        let line_of = |path: &str| {
+            let path: JsonPath = path.parse().unwrap();
+            index.resolve(&view, &path.0).and_then(|l| view.get_line(l)).map(|l| l.trim())
+        };
+
+        assert_eq!(line_of("$.config.services[1].port"), Some("\"port\": 80"));
+        assert_eq!(line_of("/config/services/1/env"), Some("\"env\": \"b\","));
+        assert_eq!(line_of("$.config.empty"), Some("\"empty\": {},"));
+        assert_eq!(line_of("/x\"y/1/1"), Some("3"));
+        assert_eq!(line_of("$.config.services[2]"), None);
+        assert_eq!(line_of("$"), Some("{"));
+    }
+
+    #[test]
+    fn test_path_of_line() {
+        let view = pretty_view(json!({"items": [{"price": 1}, {"a.b": [true]}]}));
+        let index = PathIndex::build(&view).unwrap();
+        let paths: Vec<String> = (0..view.len()).map(|l| index.path_of(&view, l).unwrap().to_string()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "$",
+                "$.items",
+                "$.items[0]",
+                "$.items[0].price",
+                "$.items[0]",
+                "$.items[1]",
+                "$.items[1][\"a.b\"]",
+                "$.items[1][\"a.b\"][0]",
+                "$.items[1][\"a.b\"]",
+                "$.items[1]",
+                "$.items",
+                "$",
+            ]
+        );
+    }
+}
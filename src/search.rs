@@ -0,0 +1,126 @@
+use crate::{op_row_lines, op_rows, LazyDiffView};
+use rayon::prelude::*;
+use similar::DiffOp;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// --- SEARCH ---
+// Plain substring search over both panes. Smart case: a query without
+// uppercase letters matches case-insensitively (ASCII folding, so byte
+// offsets stay valid for highlighting).
+
+pub struct Matcher {
+    needle: String,
+    ignore_case: bool,
+}
+
+impl Matcher {
+    pub fn new(query: &str) -> Self {
+        let ignore_case = !query.chars().any(char::is_uppercase);
+        let needle = if ignore_case { query.to_ascii_lowercase() } else { query.to_string() };
+        Self { needle, ignore_case }
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        if self.ignore_case {
+            line.to_ascii_lowercase().contains(&self.needle)
+        } else {
+            line.contains(&self.needle)
+        }
+    }
+
+    /// Byte ranges of every match in `line`.
+    pub fn find_ranges(&self, line: &str) -> Vec<Range<usize>> {
+        let found = |haystack: &str| -> Vec<Range<usize>> {
+            haystack.match_indices(self.needle.as_str()).map(|(i, m)| i..i + m.len()).collect()
+        };
+        if self.ignore_case { found(&line.to_ascii_lowercase()) } else { found(line) }
+    }
+}
+
+/// A row with a match on either side, as an op index plus offset within the op.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SearchHit {
+    pub op: usize,
+    pub offset: usize,
+}
+
+pub struct SearchResults {
+    pub query: String,
+    pub hits: Vec<SearchHit>,
+    // Matching lines per side
+    pub left_lines: usize,
+    pub right_lines: usize,
+}
+
+/// Scans every row of the diff (split or unified layout) in parallel. Returns None if cancelled.
+pub fn scan(f1: &LazyDiffView, f2: &LazyDiffView, ops: &[DiffOp], unified: bool, query: &str, cancel: &AtomicBool) -> Option<SearchResults> {
+    let matcher = Matcher::new(query);
+    let line_matches = |view: &LazyDiffView, idx: Option<usize>| idx.and_then(|i| view.get_line(i)).map(|l| matcher.is_match(l)).unwrap_or(false);
+
+    let rows: Vec<(SearchHit, bool, bool)> = ops
+        .par_iter()
+        .enumerate()
+        .flat_map(|(op_idx, op)| {
+            (0..op_rows(op, unified)).into_par_iter().filter_map(move |offset| {
+                if cancel.load(Ordering::Relaxed) {
+                    return None;
+                }
+                let (l, r) = op_row_lines(op, offset, unified);
+                let (in_left, in_right) = (line_matches(f1, l), line_matches(f2, r));
+                (in_left || in_right).then_some((SearchHit { op: op_idx, offset }, in_left, in_right))
+            })
+        })
+        .collect();
+
+    if cancel.load(Ordering::Relaxed) {
+        return None;
+    }
+    Some(SearchResults {
+        query: query.to_string(),
+        left_lines: rows.iter().filter(|r| r.1).count(),
+        right_lines: rows.iter().filter(|r| r.2).count(),
+        hits: rows.into_iter().map(|r| r.0).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContentSource;
+
+    fn view(text: &str) -> LazyDiffView {
+        LazyDiffView::from_source(ContentSource::Memory(text.as_bytes().to_vec())).unwrap()
+    }
+
+    #[test]
+    fn test_scan_both_sides() {
+        let f1 = view("{\n  \"Name\": \"a\",\n  \"id\": 1\n}");
+        let f2 = view("{\n  \"name\": \"b\",\n  \"id\": 1,\n  \"nickname\": \"c\"\n}");
+        let ops = vec![
+            DiffOp::Equal { old_index: 0, new_index: 0, len: 1 },
+            DiffOp::Replace { old_index: 1, old_len: 2, new_index: 1, new_len: 3 },
+            DiffOp::Equal { old_index: 3, new_index: 4, len: 1 },
+        ];
+
+        let results = scan(&f1, &f2, &ops, false, "name", &AtomicBool::new(false)).unwrap();
+        assert_eq!(results.hits, vec![SearchHit { op: 1, offset: 0 }, SearchHit { op: 1, offset: 2 }]);
+        assert_eq!((results.left_lines, results.right_lines), (1, 2));
+
+        // Unified rows list the two removed lines before the three added ones
+        let results = scan(&f1, &f2, &ops, true, "name", &AtomicBool::new(false)).unwrap();
+        let offsets: Vec<usize> = results.hits.iter().map(|h| h.offset).collect();
+        assert_eq!(offsets, vec![0, 2, 4]);
+
+        // An uppercase letter makes the search case-sensitive
+        let results = scan(&f1, &f2, &ops, false, "Name", &AtomicBool::new(false)).unwrap();
+        assert_eq!((results.left_lines, results.right_lines), (1, 0));
+        assert!(scan(&f1, &f2, &ops, false, "name", &AtomicBool::new(true)).is_none());
+    }
+
+    #[test]
+    fn test_find_ranges() {
+        assert_eq!(Matcher::new("ab").find_ranges("xAbyab"), vec![1..3, 4..6]);
+        assert!(Matcher::new("AB").find_ranges("xAbyab").is_empty());
+    }
+}
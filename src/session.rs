@@ -0,0 +1,192 @@
+use crate::Resolution;
+use anyhow::{bail, Context, Result};
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// --- MERGE SESSIONS ---
+// A session file records which inputs were compared, how, and the resolution
+// picked for every op, so a long merge can be stopped and resumed. Resolutions
+// are stored by op index, which only means something for the exact same bytes
+// and diff settings, so resuming against changed inputs is refused.
+
+pub const SESSION_VERSION: u32 = 1;
+// Line diff the op indices refer to
+pub const ALGORITHM: &str = "histogram";
+
+/// Identity of an input file when the session was saved.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Fingerprint {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Nanoseconds since the Unix epoch
+    pub mtime: u64,
+    /// FNV-1a (64-bit) of the contents as 16 hex digits
+    pub hash: String,
+}
+
+/// Comparison options in their command-line spelling.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    pub ignore_order: bool,
+    pub ignore_order_at: Vec<String>,
+    pub array_key: Vec<String>,
+    pub context: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Session {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<Fingerprint>,
+    pub left: Fingerprint,
+    pub right: Fingerprint,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merged: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    pub algorithm: String,
+    pub options: Options,
+    pub resolutions: Vec<Resolution>,
+}
+
+/// The inputs and options a comparison was started with.
+#[derive(Clone, Debug)]
+pub struct Setup {
+    pub base: Option<PathBuf>,
+    pub left: PathBuf,
+    pub right: PathBuf,
+    pub merged: Option<String>,
+    pub labels: Vec<String>,
+    pub options: Options,
+    // Taken before the inputs were read: a later mtime means the loaded ops
+    // may not describe the file any more
+    pub started: SystemTime,
+}
+
+impl Session {
+    /// Snapshots `setup` with the current resolutions. Fails if an input was
+    /// modified after it was loaded.
+    pub fn capture(setup: &Setup, resolutions: &[Resolution]) -> Result<Session> {
+        let started = nanos(setup.started);
+        let stamp = |path: &Path| -> Result<Fingerprint> {
+            let print = fingerprint(path)?;
+            if print.mtime >= started {
+                bail!("{} changed on disk after it was loaded", path.display());
+            }
+            Ok(print)
+        };
+        Ok(Session {
+            version: SESSION_VERSION,
+            base: setup.base.as_deref().map(stamp).transpose()?,
+            left: stamp(&setup.left)?,
+            right: stamp(&setup.right)?,
+            merged: setup.merged.clone(),
+            labels: setup.labels.clone(),
+            algorithm: ALGORITHM.to_string(),
+            options: setup.options.clone(),
+            resolutions: resolutions.to_vec(),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Session> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read session {}", path.display()))?;
+        let session: Session = serde_json::from_str(&text).with_context(|| format!("{} is not a json-diff session", path.display()))?;
+        if session.version != SESSION_VERSION {
+            bail!("Unsupported session version {} (expected {})", session.version, SESSION_VERSION);
+        }
+        if session.algorithm != ALGORITHM {
+            bail!("Session was made with the {} diff; this build uses {}", session.algorithm, ALGORITHM);
+        }
+        Ok(session)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut text = serde_json::to_string_pretty(self)?;
+        text.push('\n');
+        fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Re-hashes every input and fails on the first whose contents differ.
+    /// A differing mtime alone is fine: the bytes are what the ops describe.
+    pub fn verify(&self) -> Result<()> {
+        for saved in self.base.iter().chain([&self.left, &self.right]) {
+            let now = fingerprint(&saved.path)?;
+            if now.size != saved.size || now.hash != saved.hash {
+                bail!(
+                    "{} has changed since the session was saved ({} bytes, hash {}; now {} bytes, hash {})",
+                    saved.path.display(),
+                    saved.size,
+                    saved.hash,
+                    now.size,
+                    now.hash
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Size, mtime and content hash of `path`, which is stored absolute so a
+/// session resumes from any directory.
+pub fn fingerprint(path: &Path) -> Result<Fingerprint> {
+    let path = fs::canonicalize(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let file = File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let metadata = file.metadata()?;
+    // Mapping an empty file fails on some platforms
+    let hash = if metadata.len() == 0 { fnv1a(&[]) } else { fnv1a(&unsafe { Mmap::map(&file)? }) };
+    Ok(Fingerprint { path, size: metadata.len(), mtime: nanos(metadata.modified()?), hash: format!("{:016x}", hash) })
+}
+
+fn nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_round_trip_and_verify() {
+        let dir = std::env::temp_dir().join(format!("json-diff-session-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (left, right, file) = (dir.join("a.json"), dir.join("b.json"), dir.join("session.json"));
+        fs::write(&left, "{\"a\": 1}\n").unwrap();
+        fs::write(&right, "").unwrap();
+
+        let setup = Setup {
+            base: None,
+            left: left.clone(),
+            right: right.clone(),
+            merged: Some("out.json".to_string()),
+            labels: Vec::new(),
+            options: Options { ignore_order_at: vec!["$.tags".to_string()], context: 5, ..Default::default() },
+            started: SystemTime::now() + std::time::Duration::from_secs(60),
+        };
+        let resolutions = vec![Resolution::Unresolved, Resolution::PickRight, Resolution::Edit("x\n".to_string())];
+        Session::capture(&setup, &resolutions).unwrap().save(&file).unwrap();
+
+        let session = Session::load(&file).unwrap();
+        assert_eq!(session.resolutions, resolutions);
+        assert_eq!(session.options, setup.options);
+        assert_eq!(session.right.hash, format!("{:016x}", 0xcbf2_9ce4_8422_2325u64));
+        session.verify().unwrap();
+
+        // Same size, different bytes
+        fs::write(&left, "{\"a\": 2}\n").unwrap();
+        let err = session.verify().unwrap_err().to_string();
+        assert!(err.contains("a.json has changed since the session was saved"), "{}", err);
+
+        // Loaded before the edit above: capturing now must refuse
+        let stale = Setup { started: UNIX_EPOCH, ..setup };
+        assert!(Session::capture(&stale, &resolutions).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
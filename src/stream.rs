@@ -0,0 +1,526 @@
+use crate::structural::{ChangeKind, JsonPath, StructuralChange};
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::ops::Range;
+
+// --- STREAMING JSON (files above MAX_JSON_FORMAT_SIZE) ---
+// A pull reader over raw bytes (usually the mmap) that walks values without
+// building a tree. Readers are plain cursors, so cloning one to look ahead is
+// free and memory stays bounded by nesting depth, not file size.
+
+const MAX_DEPTH: usize = 512;
+// Objects with up to this many members are matched by key in any order;
+// larger ones fall back to an order-sensitive merge with bounded lookahead.
+const KEY_INDEX_LIMIT: usize = 4096;
+const KEY_LOOKAHEAD: usize = 64;
+// Larger values are reported as a size summary instead of being parsed
+const CAPTURE_LIMIT: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Kind {
+    Object,
+    Array,
+    String,
+    Number,
+    Bool,
+    Null,
+}
+
+#[derive(Clone)]
+pub struct JsonReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> JsonReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn skip_ws(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_ws();
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        if self.peek() != Some(byte) {
+            bail!("expected '{}' at byte {}", byte as char, self.pos);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn kind(&mut self) -> Result<Kind> {
+        Ok(match self.peek() {
+            Some(b'{') => Kind::Object,
+            Some(b'[') => Kind::Array,
+            Some(b'"') => Kind::String,
+            Some(b't') | Some(b'f') => Kind::Bool,
+            Some(b'n') => Kind::Null,
+            Some(b'-') | Some(b'0'..=b'9') => Kind::Number,
+            Some(b) => bail!("unexpected '{}' at byte {}", b as char, self.pos),
+            None => bail!("unexpected end of input"),
+        })
+    }
+
+    // pos is on the opening quote; leaves pos after the closing one
+    fn skip_string(&mut self) -> Result<()> {
+        let mut i = self.pos + 1;
+        while i < self.bytes.len() {
+            match self.bytes[i] {
+                b'"' => {
+                    self.pos = i + 1;
+                    return Ok(());
+                }
+                b'\\' => i += 2,
+                _ => i += 1,
+            }
+        }
+        bail!("unterminated string starting at byte {}", self.pos)
+    }
+
+    /// Skips the next value and returns its byte span.
+    pub fn skip_value(&mut self) -> Result<Range<usize>> {
+        let kind = self.kind()?;
+        let start = self.pos;
+        match kind {
+            Kind::String => self.skip_string()?,
+            Kind::Object | Kind::Array => {
+                let mut depth = 0usize;
+                loop {
+                    match self.bytes.get(self.pos) {
+                        Some(b'"') => {
+                            self.skip_string()?;
+                            continue;
+                        }
+                        Some(b'{') | Some(b'[') => depth += 1,
+                        Some(b'}') | Some(b']') => {
+                            depth -= 1;
+                            if depth == 0 {
+                                self.pos += 1;
+                                break;
+                            }
+                        }
+                        Some(_) => {}
+                        None => bail!("unterminated container starting at byte {}", start),
+                    }
+                    self.pos += 1;
+                }
+            }
+            _ => {
+                while self.pos < self.bytes.len() && !matches!(self.bytes[self.pos], b',' | b']' | b'}') && !self.bytes[self.pos].is_ascii_whitespace() {
+                    self.pos += 1;
+                }
+            }
+        }
+        Ok(start..self.pos)
+    }
+
+    /// Next member key of the current object, or None after consuming `}`.
+    fn next_key(&mut self) -> Result<Option<String>> {
+        match self.peek() {
+            Some(b'}') => {
+                self.pos += 1;
+                return Ok(None);
+            }
+            Some(b',') => self.pos += 1,
+            _ => {}
+        }
+        if self.peek() != Some(b'"') {
+            bail!("expected object key at byte {}", self.pos);
+        }
+        let start = self.pos;
+        self.skip_string()?;
+        let key: String = serde_json::from_slice(&self.bytes[start..self.pos])?;
+        self.expect(b':')?;
+        Ok(Some(key))
+    }
+
+    /// Whether the current array has another element; consumes `]` if not.
+    fn next_element(&mut self) -> Result<bool> {
+        match self.peek() {
+            Some(b']') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b',') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            Some(_) => Ok(true),
+            None => bail!("unexpected end of input in array"),
+        }
+    }
+
+    /// Skips the next value, parsing it only if it is small enough; a larger
+    /// one comes back as a size summary, flagged by the `true`.
+    fn capture(&mut self) -> Result<(Value, bool)> {
+        let span = self.skip_value()?;
+        let raw = &self.bytes[span];
+        if raw.len() <= CAPTURE_LIMIT {
+            return Ok((serde_json::from_slice(raw)?, false));
+        }
+        let kind = match raw[0] {
+            b'{' => "object",
+            b'[' => "array",
+            b'"' => "string",
+            _ => "value",
+        };
+        Ok((Value::String(format!("<{} of {} bytes>", kind, raw.len())), true))
+    }
+}
+
+pub fn looks_like_json(bytes: &[u8]) -> bool {
+    matches!(bytes.iter().find(|b| !b.is_ascii_whitespace()), Some(b'{') | Some(b'['))
+}
+
+/// Receives each change as it is found; an error stops the diff.
+pub type Emit<'e> = dyn FnMut(StructuralChange) -> Result<()> + 'e;
+
+/// Structural diff straight from the raw bytes of two documents. Changes are
+/// handed to `emit` in document order instead of being collected, so memory
+/// doesn't grow with the number of differences either.
+pub fn diff_streams(old: &[u8], new: &[u8], emit: &mut Emit) -> Result<()> {
+    diff_value(&mut JsonReader::new(old), &mut JsonReader::new(new), &JsonPath::root(), 0, emit)
+}
+
+fn diff_value(a: &mut JsonReader, b: &mut JsonReader, path: &JsonPath, depth: usize, emit: &mut Emit) -> Result<()> {
+    if depth > MAX_DEPTH {
+        bail!("nesting deeper than {} levels at {}", MAX_DEPTH, path);
+    }
+    match (a.kind()?, b.kind()?) {
+        (Kind::Object, Kind::Object) => {
+            a.expect(b'{')?;
+            b.expect(b'{')?;
+            diff_object(a, b, path, depth, emit)
+        }
+        (Kind::Array, Kind::Array) => {
+            a.expect(b'[')?;
+            b.expect(b'[')?;
+            diff_array(a, b, path, depth, emit)
+        }
+        // Each kind is a distinct JSON type, so this is always a type change
+        // (decided here: a summarised value would read as a string)
+        (ka, kb) if ka != kb => {
+            let ((old, old_summarised), (new, new_summarised)) = (a.capture()?, b.capture()?);
+            emit(StructuralChange { path: path.clone(), kind: ChangeKind::TypeChanged { old, new }, summarised: old_summarised || new_summarised })?;
+            Ok(())
+        }
+        _ => {
+            let (ra, rb) = (a.clone().skip_value()?, b.clone().skip_value()?);
+            if a.bytes[ra] == b.bytes[rb] {
+                a.skip_value()?;
+                b.skip_value()?;
+                return Ok(());
+            }
+            // Raw bytes differ but the values may not (e.g. string escapes)
+            let ((old, old_summarised), (new, new_summarised)) = (a.capture()?, b.capture()?);
+            if old != new {
+                emit(StructuralChange { path: path.clone(), kind: ChangeKind::Changed { old, new }, summarised: old_summarised || new_summarised })?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// Both readers are just past `{`; leaves them just past `}`
+fn diff_object(a: &mut JsonReader, b: &mut JsonReader, path: &JsonPath, depth: usize, emit: &mut Emit) -> Result<()> {
+    // Index b's members by key (value offsets only) when the object is small enough
+    let start_b = b.clone();
+    let mut scan = b.clone();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut indexed = true;
+    while let Some(key) = scan.next_key()? {
+        if index.len() == KEY_INDEX_LIMIT {
+            indexed = false;
+            break;
+        }
+        index.insert(key, scan.pos);
+        scan.skip_value()?;
+    }
+
+    if indexed {
+        let mut matched = HashSet::new();
+        while let Some(key) = a.next_key()? {
+            match index.get(&key) {
+                Some(&pos) => {
+                    let mut vb = JsonReader { bytes: b.bytes, pos };
+                    diff_value(a, &mut vb, &path.key(&key), depth + 1, emit)?;
+                    matched.insert(key);
+                }
+                None => emit(one_sided(a, path.key(&key), ChangeKind::Removed)?)?,
+            }
+        }
+        let mut rb = start_b;
+        while let Some(key) = rb.next_key()? {
+            if matched.contains(&key) {
+                rb.skip_value()?;
+            } else {
+                emit(one_sided(&mut rb, path.key(&key), ChangeKind::Added)?)?;
+            }
+        }
+        *b = rb;
+        return Ok(());
+    }
+
+    // Large object: merge members in document order
+    let mut ka = a.next_key()?;
+    let mut kb = b.next_key()?;
+    loop {
+        match (&ka, &kb) {
+            (None, None) => return Ok(()),
+            (Some(x), Some(y)) if x == y => {
+                diff_value(a, b, &path.key(x), depth + 1, emit)?;
+                ka = a.next_key()?;
+                kb = b.next_key()?;
+            }
+            (Some(x), Some(y)) if appears_later(b.clone(), x)? => {
+                emit(one_sided(b, path.key(y), ChangeKind::Added)?)?;
+                kb = b.next_key()?;
+            }
+            (Some(x), _) => {
+                emit(one_sided(a, path.key(x), ChangeKind::Removed)?)?;
+                ka = a.next_key()?;
+            }
+            (None, Some(y)) => {
+                emit(one_sided(b, path.key(y), ChangeKind::Added)?)?;
+                kb = b.next_key()?;
+            }
+        }
+    }
+}
+
+// An added or removed member or element, read from the reader's next value
+fn one_sided(reader: &mut JsonReader, path: JsonPath, kind: fn(Value) -> ChangeKind) -> Result<StructuralChange> {
+    let (value, summarised) = reader.capture()?;
+    Ok(StructuralChange { path, kind: kind(value), summarised })
+}
+
+// Whether `key` shows up within the next few members (reader is on a value)
+fn appears_later(mut reader: JsonReader, key: &str) -> Result<bool> {
+    reader.skip_value()?;
+    for _ in 0..KEY_LOOKAHEAD {
+        match reader.next_key()? {
+            Some(k) if k == key => return Ok(true),
+            Some(_) => {
+                reader.skip_value()?;
+            }
+            None => return Ok(false),
+        }
+    }
+    Ok(false)
+}
+
+// Both readers are just past `[`; leaves them just past `]`
+fn diff_array(a: &mut JsonReader, b: &mut JsonReader, path: &JsonPath, depth: usize, emit: &mut Emit) -> Result<()> {
+    let mut i = 0;
+    let (mut more_a, mut more_b) = (a.next_element()?, b.next_element()?);
+    while more_a && more_b {
+        diff_value(a, b, &path.index(i), depth + 1, emit)?;
+        i += 1;
+        more_a = a.next_element()?;
+        more_b = b.next_element()?;
+    }
+    let mut j = i;
+    while more_a {
+        emit(one_sided(a, path.index(i), ChangeKind::Removed)?)?;
+        i += 1;
+        more_a = a.next_element()?;
+    }
+    while more_b {
+        emit(one_sided(b, path.index(j), ChangeKind::Added)?)?;
+        j += 1;
+        more_b = b.next_element()?;
+    }
+    Ok(())
+}
+
+const SPACES: &[u8] = &[b' '; 128];
+
+fn write_indent<W: Write>(out: &mut W, depth: usize) -> std::io::Result<()> {
+    out.write_all(b"\n")?;
+    let mut n = depth * 2;
+    while n > 0 {
+        let chunk = n.min(SPACES.len());
+        out.write_all(&SPACES[..chunk])?;
+        n -= chunk;
+    }
+    Ok(())
+}
+
+/// Re-indents JSON byte by byte in the same layout as
+/// `serde_json::to_string_pretty`, without building a tree. Strings, numbers
+/// and literals are copied verbatim.
+pub fn write_pretty<W: Write>(bytes: &[u8], out: &mut W) -> Result<()> {
+    let mut stack: Vec<u8> = Vec::new();
+    // An opener is held back until we know whether the container is empty
+    let mut pending: Option<u8> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if let Some(open) = pending.take() {
+            let close = if open == b'{' { b'}' } else { b']' };
+            if b == close {
+                out.write_all(&[open, close])?;
+                stack.pop();
+                i += 1;
+                continue;
+            }
+            out.write_all(&[open])?;
+            write_indent(out, stack.len())?;
+        }
+        match b {
+            b'{' | b'[' => {
+                pending = Some(b);
+                stack.push(if b == b'{' { b'}' } else { b']' });
+                i += 1;
+            }
+            b'}' | b']' => {
+                if stack.pop() != Some(b) {
+                    bail!("unexpected '{}' at byte {}", b as char, i);
+                }
+                write_indent(out, stack.len())?;
+                out.write_all(&[b])?;
+                i += 1;
+            }
+            b',' => {
+                if stack.is_empty() {
+                    bail!("unexpected ',' at byte {}", i);
+                }
+                out.write_all(b",")?;
+                write_indent(out, stack.len())?;
+                i += 1;
+            }
+            b':' => {
+                out.write_all(b": ")?;
+                i += 1;
+            }
+            b'"' => {
+                let start = i;
+                i += 1;
+                loop {
+                    match bytes.get(i) {
+                        Some(b'"') => break,
+                        Some(b'\\') => i += 2,
+                        Some(_) => i += 1,
+                        None => bail!("unterminated string starting at byte {}", start),
+                    }
+                }
+                i += 1;
+                out.write_all(&bytes[start..i])?;
+            }
+            _ => {
+                let start = i;
+                while i < bytes.len() && !matches!(bytes[i], b',' | b':' | b'{' | b'}' | b'[' | b']' | b'"') && !bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                out.write_all(&bytes[start..i])?;
+            }
+        }
+    }
+
+    if pending.is_some() || !stack.is_empty() {
+        bail!("unexpected end of input");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structural::{diff_values, DiffOptions};
+    use serde_json::json;
+
+    fn collect(old: &[u8], new: &[u8]) -> Result<Vec<StructuralChange>> {
+        let mut changes = Vec::new();
+        diff_streams(old, new, &mut |change| {
+            changes.push(change);
+            Ok(())
+        })?;
+        Ok(changes)
+    }
+
+    fn sorted(mut changes: Vec<StructuralChange>) -> Vec<String> {
+        let mut out: Vec<String> = changes.drain(..).map(|c| format!("{} {} {}", c.kind.marker(), c.path, c.kind.describe())).collect();
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn test_streaming_matches_tree_diff() {
+        let a = r#"{"b": [1, 2, {"x": "a\"b"}], "a": {"k": true, "gone": null}, "n": 1, "s": "A"}"#;
+        let b = r#"{"a": {"k": false, "new": [1]}, "b": [1, 3], "n": "1", "s": "A"}"#;
+        let streamed = collect(a.as_bytes(), b.as_bytes()).unwrap();
+        let tree = diff_values(&serde_json::from_str(a).unwrap(), &serde_json::from_str(b).unwrap(), &DiffOptions::default());
+        assert_eq!(sorted(streamed), sorted(tree));
+    }
+
+    #[test]
+    fn test_large_object_merges_in_order() {
+        let mut a = serde_json::Map::new();
+        let mut b = serde_json::Map::new();
+        for i in 0..KEY_INDEX_LIMIT + 10 {
+            a.insert(format!("k{:05}", i), json!(i));
+            if i != 3 {
+                b.insert(format!("k{:05}", i), json!(if i == 7 { 0 } else { i }));
+            }
+        }
+        b.insert("k00003x".to_string(), json!("inserted"));
+        let (a, b) = (Value::Object(a).to_string(), Value::Object(b).to_string());
+
+        let changes = sorted(collect(a.as_bytes(), b.as_bytes()).unwrap());
+        assert_eq!(changes, vec!["+ $.k00003x \"inserted\"", "- $.k00003 3", "~ $.k00007 7 → 0"]);
+    }
+
+    #[test]
+    fn test_large_values_are_flagged_as_summarised() {
+        let big = "x".repeat(CAPTURE_LIMIT);
+        let a = r#"{"s": 1, "t": [1]}"#;
+        let b = format!(r#"{{"s": 2, "t": [1, "{}"], "u": {{"k": "{}"}}}}"#, big, big);
+        let changes = collect(a.as_bytes(), b.as_bytes()).unwrap();
+        let flags: Vec<(String, bool)> = changes.iter().map(|c| (c.path.to_string(), c.summarised)).collect();
+        assert_eq!(flags, vec![("$.s".to_string(), false), ("$.t[1]".to_string(), true), ("$.u".to_string(), true)]);
+        assert_eq!(changes[1].kind, ChangeKind::Added(Value::String(format!("<string of {} bytes>", CAPTURE_LIMIT + 2))));
+    }
+
+    #[test]
+    fn test_write_pretty_matches_serde_layout() {
+        let raw = r#"{"a":[1,{"b":null,"c":[]},"x,\"y:"],"e":{},"n":-1.5}"#;
+        let mut out = Vec::new();
+        write_pretty(raw.as_bytes(), &mut out).unwrap();
+        let value: Value = serde_json::from_str(raw).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), serde_json::to_string_pretty(&value).unwrap());
+        assert!(write_pretty(b"{\"a\": [1}", &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_changes_are_emitted_as_found() {
+        // An error from the receiver (say, a closed stdout) stops the walk
+        let mut seen = 0;
+        let res = diff_streams(b"[1, 2, 3, 4]", b"[5, 6, 7, 8]", &mut |_| {
+            seen += 1;
+            if seen == 2 { bail!("stop") } else { Ok(()) }
+        });
+        assert_eq!((res.unwrap_err().to_string(), seen), ("stop".to_string(), 2));
+    }
+
+    #[test]
+    fn test_malformed_input_is_an_error() {
+        assert!(collect(b"{\"a\": [1, 2}", b"{\"a\": 1}").is_err());
+        assert!(looks_like_json(b"  \n[1]"));
+        assert!(!looks_like_json(b"plain text"));
+    }
+}
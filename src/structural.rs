@@ -0,0 +1,571 @@
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+// --- STRUCTURAL (SEMANTIC) DIFF ---
+// Walks two parsed JSON trees and reports typed changes with their paths,
+// so reordered keys or reflowed values don't show up as noise.
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// A location inside a JSON document, rendered as `$.items[3].price`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct JsonPath(pub Vec<PathSegment>);
+
+impl JsonPath {
+    pub fn root() -> Self {
+        JsonPath(Vec::new())
+    }
+
+    pub fn key(&self, key: &str) -> Self {
+        let mut segments = self.0.clone();
+        segments.push(PathSegment::Key(key.to_string()));
+        JsonPath(segments)
+    }
+
+    pub fn index(&self, index: usize) -> Self {
+        let mut segments = self.0.clone();
+        segments.push(PathSegment::Index(index));
+        JsonPath(segments)
+    }
+
+    /// RFC 6901 JSON Pointer form (`/items/3/price`).
+    pub fn to_pointer(&self) -> String {
+        let mut out = String::new();
+        for seg in &self.0 {
+            out.push('/');
+            match seg {
+                PathSegment::Key(k) => out.push_str(&k.replace('~', "~0").replace('/', "~1")),
+                PathSegment::Index(i) => out.push_str(&i.to_string()),
+            }
+        }
+        out
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for seg in &self.0 {
+            match seg {
+                PathSegment::Key(k) if is_identifier(k) => write!(f, ".{}", k)?,
+                PathSegment::Key(k) => write!(f, "[{}]", Value::String(k.clone()))?,
+                PathSegment::Index(i) => write!(f, "[{}]", i)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a concrete path: JSONPath (`$.items[3].price`) or, with a leading
+/// `/`, a JSON Pointer. Pointer tokens are kept as keys; a numeric key also
+/// selects that index when the container turns out to be an array.
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        if let Some(pointer) = trimmed.strip_prefix('/') {
+            let keys = pointer.split('/').map(|t| PathSegment::Key(t.replace("~1", "/").replace("~0", "~")));
+            return Ok(JsonPath(keys.collect()));
+        }
+        let pattern: PathPattern = trimmed.parse()?;
+        let segments = pattern.0.into_iter().map(|seg| match seg {
+            PatternSegment::Key(k) => Ok(PathSegment::Key(k)),
+            PatternSegment::Index(i) => Ok(PathSegment::Index(i)),
+            PatternSegment::AnyKey | PatternSegment::AnyIndex => Err(format!("wildcards are not allowed in \"{}\"", s)),
+        });
+        Ok(JsonPath(segments.collect::<Result<_, _>>()?))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PatternSegment {
+    Key(String),
+    Index(usize),
+    AnyKey,   // .*
+    AnyIndex, // [*]
+}
+
+/// A JSONPath-like pattern such as `$.users[*].roles` or `tags`, matched
+/// against whole paths. `*` and `[*]` match any single key or index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathPattern(Vec<PatternSegment>);
+
+impl PathPattern {
+    pub fn matches(&self, path: &JsonPath) -> bool {
+        self.0.len() == path.0.len()
+            && self.0.iter().zip(&path.0).all(|(p, s)| match (p, s) {
+                (PatternSegment::Key(a), PathSegment::Key(b)) => a == b,
+                (PatternSegment::Index(a), PathSegment::Index(b)) => a == b,
+                (PatternSegment::AnyKey, PathSegment::Key(_)) => true,
+                (PatternSegment::AnyIndex, PathSegment::Index(_)) => true,
+                _ => false,
+            })
+    }
+}
+
+/// Renders in the syntax `from_str` reads back (`$.users[*]["a.b"]`).
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for seg in &self.0 {
+            match seg {
+                PatternSegment::Key(k) if is_identifier(k) => write!(f, ".{}", k)?,
+                // Quoted keys have no escapes, so pick the quote the key lacks
+                PatternSegment::Key(k) if k.contains('"') => write!(f, "['{}']", k)?,
+                PatternSegment::Key(k) => write!(f, "[\"{}\"]", k)?,
+                PatternSegment::Index(i) => write!(f, "[{}]", i)?,
+                PatternSegment::AnyKey => write!(f, ".*")?,
+                PatternSegment::AnyIndex => write!(f, "[*]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let mut rest = match trimmed.strip_prefix('$') {
+            Some(after) if after.is_empty() || after.starts_with(['.', '[']) => after,
+            _ => trimmed,
+        };
+        let mut segments = Vec::new();
+        // Allow a leading bare key: `users[*]` == `$.users[*]`
+        if !rest.is_empty() && !rest.starts_with(['.', '[']) {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            segments.push(key_segment(&rest[..end]));
+            rest = &rest[end..];
+        }
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                if end == 0 {
+                    return Err(format!("empty key in path \"{}\"", s));
+                }
+                segments.push(key_segment(&after[..end]));
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = if after.starts_with('"') || after.starts_with('\'') {
+                    // Quoted key: ["a.b"] or ['a.b']
+                    let quote = &after[..1];
+                    let close = after[1..].find(quote).ok_or_else(|| format!("unterminated quote in path \"{}\"", s))? + 1;
+                    segments.push(PatternSegment::Key(after[1..close].to_string()));
+                    close + 1
+                } else {
+                    let close = after.find(']').ok_or_else(|| format!("missing ']' in path \"{}\"", s))?;
+                    let inner = &after[..close];
+                    segments.push(match inner {
+                        "*" => PatternSegment::AnyIndex,
+                        _ => PatternSegment::Index(inner.parse().map_err(|_| format!("invalid index \"{}\" in path \"{}\"", inner, s))?),
+                    });
+                    close
+                };
+                rest = after[end..].strip_prefix(']').ok_or_else(|| format!("missing ']' in path \"{}\"", s))?;
+            } else {
+                return Err(format!("unexpected \"{}\" in path \"{}\"", rest, s));
+            }
+        }
+        Ok(PathPattern(segments))
+    }
+}
+
+/// `--array-key` rule: elements matching `elements` (e.g. `users[*]`) are
+/// paired across both sides by the value of their `key` field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrayKey {
+    pub elements: PathPattern,
+    pub key: String,
+}
+
+impl FromStr for ArrayKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, key) = s.rsplit_once('=').ok_or_else(|| format!("expected PATH=FIELD, got \"{}\"", s))?;
+        if key.is_empty() {
+            return Err(format!("missing key field in \"{}\"", s));
+        }
+        Ok(ArrayKey { elements: pattern.parse()?, key: key.to_string() })
+    }
+}
+
+impl fmt::Display for ArrayKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.elements, self.key)
+    }
+}
+
+fn key_segment(key: &str) -> PatternSegment {
+    if key == "*" { PatternSegment::AnyKey } else { PatternSegment::Key(key.to_string()) }
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == '$' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeKind {
+    Added(Value),
+    Removed(Value),
+    Changed { old: Value, new: Value },
+    TypeChanged { old: Value, new: Value },
+    /// An array element paired by identity key now sits at a new position
+    Moved { from: JsonPath },
+}
+
+impl ChangeKind {
+    pub fn marker(&self) -> char {
+        match self {
+            ChangeKind::Added(_) => '+',
+            ChangeKind::Removed(_) => '-',
+            ChangeKind::Changed { .. } => '~',
+            ChangeKind::TypeChanged { .. } => '!',
+            ChangeKind::Moved { .. } => '>',
+        }
+    }
+
+    /// One-line summary of the values involved, e.g. `1 → "1" (number → string)`.
+    pub fn describe(&self) -> String {
+        match self {
+            ChangeKind::Added(v) | ChangeKind::Removed(v) => v.to_string(),
+            ChangeKind::Changed { old, new } => format!("{} → {}", old, new),
+            ChangeKind::TypeChanged { old, new } => {
+                format!("{} → {} ({} → {})", old, new, type_name(old), type_name(new))
+            }
+            ChangeKind::Moved { from } => format!("moved from {}", from),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructuralChange {
+    pub path: JsonPath,
+    pub kind: ChangeKind,
+    /// A value in `kind` only stands in for one too large to keep: streamed
+    /// inputs report values over 4 KB as `"<object of N bytes>"`
+    pub summarised: bool,
+}
+
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DiffOptions {
+    /// Compare every array as a multiset
+    pub ignore_order: bool,
+    /// Compare arrays at these paths as multisets
+    pub unordered_paths: Vec<PathPattern>,
+    /// Pair array elements by an identity field instead of by position
+    pub array_keys: Vec<ArrayKey>,
+}
+
+impl DiffOptions {
+    /// True when nothing changes how arrays are compared.
+    pub fn is_default(&self) -> bool {
+        !self.ignore_order && self.unordered_paths.is_empty() && self.array_keys.is_empty()
+    }
+
+    fn is_unordered(&self, path: &JsonPath) -> bool {
+        self.ignore_order || self.unordered_paths.iter().any(|p| p.matches(path))
+    }
+
+    fn array_key(&self, path: &JsonPath) -> Option<&str> {
+        let element = path.index(0);
+        self.array_keys.iter().find(|k| k.elements.matches(&element)).map(|k| k.key.as_str())
+    }
+}
+
+/// Compares two JSON trees. Object members are matched by key, array
+/// elements by position unless `options` marks the array as unordered.
+pub fn diff_values(old: &Value, new: &Value, options: &DiffOptions) -> Vec<StructuralChange> {
+    let mut changes = Vec::new();
+    walk(&JsonPath::root(), old, new, options, &mut changes);
+    changes
+}
+
+fn walk(path: &JsonPath, old: &Value, new: &Value, options: &DiffOptions, out: &mut Vec<StructuralChange>) {
+    if let (Value::Array(a), Value::Array(b), Some(key)) = (old, new, options.array_key(path)) {
+        if walk_keyed(path, a, b, key, options, out) {
+            return;
+        }
+    }
+
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (k, va) in a {
+                match b.get(k) {
+                    Some(vb) => walk(&path.key(k), va, vb, options, out),
+                    None => out.push(StructuralChange { path: path.key(k), kind: ChangeKind::Removed(va.clone()), summarised: false }),
+                }
+            }
+            for (k, vb) in b {
+                if !a.contains_key(k) {
+                    out.push(StructuralChange { path: path.key(k), kind: ChangeKind::Added(vb.clone()), summarised: false });
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if options.is_unordered(path) => {
+            // Multiset comparison: equal elements cancel out wherever they sit
+            let mut pool: HashMap<String, Vec<usize>> = HashMap::new();
+            for (i, vb) in b.iter().enumerate().rev() {
+                pool.entry(canonical(vb, &path.index(i), options)).or_default().push(i);
+            }
+            for (i, va) in a.iter().enumerate() {
+                let matched = pool.get_mut(&canonical(va, &path.index(i), options)).and_then(|idx| idx.pop());
+                if matched.is_none() {
+                    out.push(StructuralChange { path: path.index(i), kind: ChangeKind::Removed(va.clone()), summarised: false });
+                }
+            }
+            let mut unmatched: Vec<usize> = pool.into_values().flatten().collect();
+            unmatched.sort_unstable();
+            for i in unmatched {
+                out.push(StructuralChange { path: path.index(i), kind: ChangeKind::Added(b[i].clone()), summarised: false });
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            let common = a.len().min(b.len());
+            for i in 0..common {
+                walk(&path.index(i), &a[i], &b[i], options, out);
+            }
+            for (i, va) in a.iter().enumerate().skip(common) {
+                out.push(StructuralChange { path: path.index(i), kind: ChangeKind::Removed(va.clone()), summarised: false });
+            }
+            for (i, vb) in b.iter().enumerate().skip(common) {
+                out.push(StructuralChange { path: path.index(i), kind: ChangeKind::Added(vb.clone()), summarised: false });
+            }
+        }
+        _ if type_name(old) != type_name(new) => {
+            out.push(StructuralChange {
+                path: path.clone(),
+                kind: ChangeKind::TypeChanged { old: old.clone(), new: new.clone() },
+                summarised: false,
+            });
+        }
+        _ => {
+            if old != new {
+                out.push(StructuralChange {
+                    path: path.clone(),
+                    kind: ChangeKind::Changed { old: old.clone(), new: new.clone() },
+                    summarised: false,
+                });
+            }
+        }
+    }
+}
+
+// Identity of each element, or None if one lacks the key or a key repeats
+fn element_keys(items: &[Value], key: &str) -> Option<HashMap<String, usize>> {
+    let mut keys = HashMap::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        let id = item.get(key)?.to_string();
+        if keys.insert(id, i).is_some() {
+            return None;
+        }
+    }
+    Some(keys)
+}
+
+/// Pairs elements by `key` and diffs each pair field by field. Returns false
+/// (emitting nothing) when the arrays can't be keyed, so the caller falls back
+/// to the ordered or multiset comparison.
+fn walk_keyed(path: &JsonPath, a: &[Value], b: &[Value], key: &str, options: &DiffOptions, out: &mut Vec<StructuralChange>) -> bool {
+    let (Some(_), Some(b_keys)) = (element_keys(a, key), element_keys(b, key)) else {
+        return false;
+    };
+
+    // new index -> old index for every pair
+    let mut pair_of = vec![None; b.len()];
+    for (i, va) in a.iter().enumerate() {
+        match b_keys.get(&va[key].to_string()) {
+            Some(&j) => pair_of[j] = Some(i),
+            None => out.push(StructuralChange { path: path.index(i), kind: ChangeKind::Removed(va.clone()), summarised: false }),
+        }
+    }
+
+    // Pairs on the longest increasing run of old indices kept their relative
+    // order; shifts caused by insertions or removals are not moves
+    let paired: Vec<usize> = pair_of.iter().flatten().copied().collect();
+    let in_order = longest_increasing(&paired);
+
+    for (j, vb) in b.iter().enumerate() {
+        match pair_of[j] {
+            Some(i) => {
+                if !in_order.contains(&i) {
+                    out.push(StructuralChange { path: path.index(j), kind: ChangeKind::Moved { from: path.index(i) }, summarised: false });
+                }
+                walk(&path.index(j), &a[i], vb, options, out);
+            }
+            None => out.push(StructuralChange { path: path.index(j), kind: ChangeKind::Added(vb.clone()), summarised: false }),
+        }
+    }
+    true
+}
+
+/// Values on one longest strictly increasing subsequence (patience sorting).
+fn longest_increasing(seq: &[usize]) -> HashSet<usize> {
+    let mut tails: Vec<usize> = Vec::new(); // positions in seq
+    let mut prev = vec![usize::MAX; seq.len()];
+    for (pos, &v) in seq.iter().enumerate() {
+        let slot = tails.partition_point(|&t| seq[t] < v);
+        if slot > 0 {
+            prev[pos] = tails[slot - 1];
+        }
+        if slot == tails.len() {
+            tails.push(pos);
+        } else {
+            tails[slot] = pos;
+        }
+    }
+    let mut result = HashSet::new();
+    let mut cur = tails.last().copied().unwrap_or(usize::MAX);
+    while cur != usize::MAX {
+        result.insert(seq[cur]);
+        cur = prev[cur];
+    }
+    result
+}
+
+// Order-insensitive key for multiset matching. Object keys are already
+// sorted (serde_json Map); unordered arrays are sorted here.
+fn canonical(value: &Value, path: &JsonPath, options: &DiffOptions) -> String {
+    match value {
+        Value::Object(map) => {
+            let members: Vec<String> = map
+                .iter()
+                .map(|(k, v)| format!("{}:{}", Value::String(k.clone()), canonical(v, &path.key(k), options)))
+                .collect();
+            format!("{{{}}}", members.join(","))
+        }
+        Value::Array(arr) => {
+            let mut items: Vec<String> = arr.iter().enumerate().map(|(i, v)| canonical(v, &path.index(i), options)).collect();
+            if options.is_unordered(path) {
+                items.sort_unstable();
+            }
+            format!("[{}]", items.join(","))
+        }
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reordered_keys_are_equal() {
+        let a = json!({"a": 1, "b": {"c": [1, 2]}});
+        let b: Value = serde_json::from_str(r#"{"b": {"c": [1, 2]}, "a": 1}"#).unwrap();
+        assert!(diff_values(&a, &b, &DiffOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_typed_changes_with_paths() {
+        let a = json!({"name": "x", "n": 1, "gone": true, "list": [1, 2, 3]});
+        let b = json!({"name": "y", "n": "1", "new key": null, "list": [1, 2]});
+        let changes = diff_values(&a, &b, &DiffOptions::default());
+
+        let find = |p: &str| changes.iter().find(|c| c.path.to_string() == p).map(|c| &c.kind);
+        assert_eq!(find("$.name"), Some(&ChangeKind::Changed { old: json!("x"), new: json!("y") }));
+        assert_eq!(find("$.n"), Some(&ChangeKind::TypeChanged { old: json!(1), new: json!("1") }));
+        assert_eq!(find("$.gone"), Some(&ChangeKind::Removed(json!(true))));
+        assert_eq!(find("$[\"new key\"]"), Some(&ChangeKind::Added(Value::Null)));
+        assert_eq!(find("$.list[2]"), Some(&ChangeKind::Removed(json!(3))));
+        assert_eq!(changes.len(), 5);
+    }
+
+    #[test]
+    fn test_ignore_order_per_path() {
+        let a = json!({"tags": ["x", "y", "y"], "roles": [{"r": [1, 2]}, "admin"], "seq": [1, 2]});
+        let b = json!({"tags": ["y", "x", "y"], "roles": ["admin", {"r": [2, 1]}], "seq": [2, 1]});
+        let options = DiffOptions {
+            unordered_paths: vec!["tags".parse().unwrap(), "$.roles".parse().unwrap(), "$.roles[*].r".parse().unwrap()],
+            ..Default::default()
+        };
+        let changes = diff_values(&a, &b, &options);
+        let paths: Vec<String> = changes.iter().map(|c| c.path.to_string()).collect();
+        assert_eq!(paths, vec!["$.seq[0]", "$.seq[1]"]);
+
+        let changes = diff_values(&json!([1, 2, 2]), &json!([2, 3, 1]), &DiffOptions { ignore_order: true, ..Default::default() });
+        assert_eq!(
+            changes,
+            vec![
+                StructuralChange { path: JsonPath::root().index(2), kind: ChangeKind::Removed(json!(2)), summarised: false },
+                StructuralChange { path: JsonPath::root().index(1), kind: ChangeKind::Added(json!(3)), summarised: false },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_array_key_pairs_elements() {
+        let a = json!({"users": [{"id": 1, "n": "a"}, {"id": 2, "n": "b"}, {"id": 3, "n": "c"}, {"id": 4, "n": "d"}]});
+        let b = json!({"users": [{"id": 0, "n": "new"}, {"id": 1, "n": "a"}, {"id": 3, "n": "C"}, {"id": 4, "n": "d"}, {"id": 2, "n": "b"}]});
+        let options = DiffOptions { array_keys: vec!["users[*]=id".parse().unwrap()], ..Default::default() };
+
+        let summary: Vec<String> = diff_values(&a, &b, &options)
+            .iter()
+            .map(|c| format!("{} {} {}", c.kind.marker(), c.path, c.kind.describe()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "+ $.users[0] {\"id\":0,\"n\":\"new\"}",
+                "~ $.users[2].n \"c\" → \"C\"",
+                "> $.users[4] moved from $.users[1]",
+            ]
+        );
+
+        // Duplicate keys fall back to positional matching
+        let dup = json!({"users": [{"id": 1}, {"id": 1}]});
+        assert_eq!(diff_values(&dup, &dup, &options), vec![]);
+        assert!("users[*]".parse::<ArrayKey>().is_err());
+    }
+
+    #[test]
+    fn test_path_pattern_parsing() {
+        let p: PathPattern = "$.users[*][\"a.b\"].*[2]".parse().unwrap();
+        assert!(p.matches(&JsonPath::root().key("users").index(7).key("a.b").key("x").index(2)));
+        assert!(!p.matches(&JsonPath::root().key("users").index(7).key("a.b").key("x").index(3)));
+        assert!("$.a[".parse::<PathPattern>().is_err());
+
+        // Display round-trips, including keys that need quoting
+        for text in ["$.users[*][\"a.b\"].*[2]", "$['say \"hi\"'][\"*\"]", "$"] {
+            assert_eq!(text.parse::<PathPattern>().unwrap().to_string(), text);
+        }
+        assert_eq!("users[*]=id".parse::<ArrayKey>().unwrap().to_string(), "$.users[*]=id");
+    }
+
+    #[test]
+    fn test_pointer_escaping() {
+        let p = JsonPath::root().key("a/b").key("m~n").index(0);
+        assert_eq!(p.to_pointer(), "/a~1b/m~0n/0");
+
+        // Parsed back, pointer tokens stay keys; JSONPath indices are typed
+        let parsed: JsonPath = "/a~1b/m~0n/0".parse().unwrap();
+        assert_eq!(parsed, JsonPath::root().key("a/b").key("m~n").key("0"));
+        assert_eq!("$.a[1]".parse::<JsonPath>().unwrap(), JsonPath::root().key("a").index(1));
+        assert!("$.a[*]".parse::<JsonPath>().is_err());
+    }
+}
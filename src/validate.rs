@@ -0,0 +1,316 @@
+use std::fmt;
+
+// --- INCREMENTAL JSON VALIDATION ---
+// Checks a document's syntax as it is written, a chunk at a time, without
+// keeping any of it: memory is bounded by nesting depth. Chunks can be
+// tagged (with an op index) so an error names the part of the output it is in.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxError {
+    /// 1-based position in the whole document (column in characters)
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    /// Tag of the chunk the error is in, and its line within that chunk (0-based)
+    pub tag: usize,
+    pub tag_line: usize,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.line, self.column)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Escape {
+    None,
+    Backslash,
+    Hex(u8), // digits left in \uXXXX
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Number {
+    Minus,
+    Zero,
+    Int,
+    Point,
+    Frac,
+    E,
+    ExpSign,
+    Exp,
+}
+
+impl Number {
+    fn next(self, b: u8) -> Option<Number> {
+        use Number::*;
+        Some(match (self, b) {
+            (Minus, b'0') => Zero,
+            (Minus | Int, b'0'..=b'9') => Int,
+            (Zero | Int, b'.') => Point,
+            (Point | Frac, b'0'..=b'9') => Frac,
+            (Zero | Int | Frac, b'e' | b'E') => E,
+            (E, b'+' | b'-') => ExpSign,
+            (E | ExpSign | Exp, b'0'..=b'9') => Exp,
+            _ => return None,
+        })
+    }
+
+    fn complete(self) -> bool {
+        matches!(self, Number::Zero | Number::Int | Number::Frac | Number::Exp)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Value,
+    ArrayFirst,  // after `[`: a value or `]`
+    ObjectFirst, // after `{`: a key or `}`
+    Key,         // after `,` in an object
+    Colon,
+    AfterValue,
+    Str { key: bool, escape: Escape },
+    Number(Number),
+    Literal(&'static [u8]), // bytes still expected
+    Done,
+}
+
+// Where an error would be reported right now
+#[derive(Clone, Copy)]
+struct Position {
+    line: usize,
+    column: usize,
+    tag: usize,
+    tag_line: usize,
+}
+
+pub struct Validator {
+    // Open containers with where they were opened
+    stack: Vec<(u8, Position)>,
+    state: State,
+    pos: Position,
+}
+
+impl Default for Validator {
+    fn default() -> Self {
+        Validator { stack: Vec::new(), state: State::Value, pos: Position { line: 1, column: 1, tag: 0, tag_line: 0 } }
+    }
+}
+
+impl Validator {
+    /// Tags the bytes fed from now on.
+    pub fn tag(&mut self, tag: usize) {
+        self.pos.tag = tag;
+        self.pos.tag_line = 0;
+    }
+
+    /// Feeds the next bytes of the document. Returns the first syntax error;
+    /// feeding more after an error is meaningless.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<(), SyntaxError> {
+        for &b in bytes {
+            if let Err(message) = self.step(b) {
+                return Err(self.error(self.pos, message));
+            }
+            if b == b'\n' {
+                self.pos.line += 1;
+                self.pos.column = 1;
+                self.pos.tag_line += 1;
+            } else if b & 0xC0 != 0x80 {
+                // Count characters, not UTF-8 continuation bytes
+                self.pos.column += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the document ended after one complete value. An unclosed
+    /// container is reported where it was opened, which is usually the culprit.
+    pub fn finish(&self) -> Result<(), SyntaxError> {
+        match self.state {
+            State::Done => Ok(()),
+            State::Number(n) if n.complete() && self.stack.is_empty() => Ok(()),
+            State::Str { .. } => Err(self.error(self.pos, "EOF while parsing a string".to_string())),
+            _ => match self.stack.last() {
+                Some(&(open, at)) => Err(self.error(at, format!("`{}` is never closed", open as char))),
+                None => Err(self.error(self.pos, "EOF while parsing a value".to_string())),
+            },
+        }
+    }
+
+    fn error(&self, at: Position, message: String) -> SyntaxError {
+        SyntaxError { line: at.line, column: at.column, message, tag: at.tag, tag_line: at.tag_line }
+    }
+
+    fn step(&mut self, b: u8) -> Result<(), String> {
+        match self.state {
+            State::Str { key, escape } => return self.string_byte(key, escape, b),
+            State::Literal(rest) => {
+                if b != rest[0] {
+                    return Err("invalid literal".to_string());
+                }
+                self.state = State::Literal(&rest[1..]);
+                if rest.len() == 1 {
+                    self.end_value();
+                }
+                return Ok(());
+            }
+            State::Number(n) => match n.next(b) {
+                Some(next) => {
+                    self.state = State::Number(next);
+                    return Ok(());
+                }
+                // The byte after a number belongs to what follows it
+                None if n.complete() => self.end_value(),
+                None => return Err("invalid number".to_string()),
+            },
+            _ => {}
+        }
+        if matches!(b, b' ' | b'\t' | b'\n' | b'\r') {
+            return Ok(());
+        }
+
+        match self.state {
+            State::ArrayFirst if b == b']' => self.close(),
+            State::Value if b == b']' && self.stack.last().is_some_and(|&(open, _)| open == b'[') => Err("trailing comma".to_string()),
+            State::Value | State::ArrayFirst => self.begin_value(b),
+            State::ObjectFirst | State::Key => match b {
+                b'"' => {
+                    self.state = State::Str { key: true, escape: Escape::None };
+                    Ok(())
+                }
+                b'}' if matches!(self.state, State::ObjectFirst) => self.close(),
+                b'}' => Err("trailing comma".to_string()),
+                _ => Err("key must be a string".to_string()),
+            },
+            State::Colon if b == b':' => {
+                self.state = State::Value;
+                Ok(())
+            }
+            State::Colon => Err("expected `:`".to_string()),
+            State::AfterValue => match (b, self.stack.last().map(|&(open, _)| open)) {
+                (b',', Some(b'[')) => {
+                    self.state = State::Value;
+                    Ok(())
+                }
+                (b',', _) => {
+                    self.state = State::Key;
+                    Ok(())
+                }
+                (b']', Some(b'[')) | (b'}', Some(b'{')) => self.close(),
+                (_, Some(b'[')) => Err("expected `,` or `]`".to_string()),
+                _ => Err("expected `,` or `}`".to_string()),
+            },
+            State::Done if matches!(b, b']' | b'}') => Err(format!("unmatched `{}`", b as char)),
+            State::Done => Err("trailing characters after the top-level value".to_string()),
+            State::Str { .. } | State::Literal(_) | State::Number(_) => unreachable!("handled above"),
+        }
+    }
+
+    fn begin_value(&mut self, b: u8) -> Result<(), String> {
+        self.state = match b {
+            b'{' | b'[' => {
+                self.stack.push((b, self.pos));
+                if b == b'{' { State::ObjectFirst } else { State::ArrayFirst }
+            }
+            b'"' => State::Str { key: false, escape: Escape::None },
+            b'-' => State::Number(Number::Minus),
+            b'0' => State::Number(Number::Zero),
+            b'1'..=b'9' => State::Number(Number::Int),
+            b't' => State::Literal(b"rue"),
+            b'f' => State::Literal(b"alse"),
+            b'n' => State::Literal(b"ull"),
+            _ => return Err("expected value".to_string()),
+        };
+        Ok(())
+    }
+
+    fn string_byte(&mut self, key: bool, escape: Escape, b: u8) -> Result<(), String> {
+        let escape = match (escape, b) {
+            (Escape::None, b'"') => {
+                if key {
+                    self.state = State::Colon;
+                } else {
+                    self.end_value();
+                }
+                return Ok(());
+            }
+            (Escape::None, b'\\') => Escape::Backslash,
+            (Escape::None, 0x00..=0x1F) => return Err("control character in string".to_string()),
+            (Escape::None, _) => Escape::None,
+            (Escape::Backslash, b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => Escape::None,
+            (Escape::Backslash, b'u') => Escape::Hex(4),
+            (Escape::Hex(n), _) if b.is_ascii_hexdigit() => if n == 1 { Escape::None } else { Escape::Hex(n - 1) },
+            _ => return Err("invalid escape".to_string()),
+        };
+        self.state = State::Str { key, escape };
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), String> {
+        self.stack.pop();
+        self.end_value();
+        Ok(())
+    }
+
+    fn end_value(&mut self) {
+        self.state = if self.stack.is_empty() { State::Done } else { State::AfterValue };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(chunks: &[&str]) -> Result<(), SyntaxError> {
+        let mut v = Validator::default();
+        for (tag, chunk) in chunks.iter().enumerate() {
+            v.tag(tag);
+            v.feed(chunk.as_bytes())?;
+        }
+        v.finish()
+    }
+
+    #[test]
+    fn test_accepts_what_serde_accepts() {
+        let docs = [
+            "{\"a\": [1, -0.5e+3, true, false, null, \"x\\\"\\u00e9\\n\"], \"b\": {}}\n",
+            "[]",
+            "  42  ",
+            "\"é\"",
+            "{\"a\":1,}",
+            "[1,,2]",
+            "[01]",
+            "{\"a\" 1}",
+            "[1] [2]",
+            "[\"\\x\"]",
+            "{1: 2}",
+            "[tru]",
+            "",
+            "[1.]",
+        ];
+        for doc in docs {
+            // Byte by byte, to cross every chunk boundary
+            let chunks: Vec<String> = doc.chars().map(String::from).collect();
+            let chunks: Vec<&str> = chunks.iter().map(String::as_str).collect();
+            let expected = serde_json::from_str::<serde_json::Value>(doc).is_ok();
+            assert_eq!(check(&chunks).is_ok(), expected, "{:?}", doc);
+        }
+    }
+
+    #[test]
+    fn test_error_positions_and_tags() {
+        // Pick Both on the last member leaves a comma before the closing brace
+        let err = check(&["{\n  \"a\": 1,\n", "  \"b\": 2,\n  \"b\": 3,\n", "}\n"]).unwrap_err();
+        assert_eq!((err.line, err.column, err.tag, err.tag_line), (5, 1, 2, 0));
+        assert_eq!(err.to_string(), "trailing comma at line 5, column 1");
+
+        let err = check(&["[\n  1\n  2\n]"]).unwrap_err();
+        assert_eq!((err.line, err.column, err.message.as_str()), (3, 3, "expected `,` or `]`"));
+
+        // Unbalanced: reported at the innermost opener that never closes
+        let err = check(&["{\n", "  \"a\": [\n", "    1\n", "}\n"]).unwrap_err();
+        assert_eq!((err.line, err.column, err.message.as_str()), (4, 1, "expected `,` or `]`"));
+        let err = check(&["[\n", "  {\"a\": 1},\n", "  {\"b\": [2\n"]).unwrap_err();
+        assert_eq!((err.line, err.column, err.tag, err.tag_line, err.message.as_str()), (3, 9, 2, 0, "`[` is never closed"));
+    }
+}